@@ -0,0 +1,55 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use engine::{
+    GenerateMoves, PextMoveGen, Position, HYPERBOLA_QUINTESSENCE_MOVE_GEN, MAGIC_BITBOARD_MOVE_GEN,
+};
+
+// Same positions the UCI `bench` command runs perft on
+const PERFT_BENCHMARK_FENS_AND_DEPTHS: &[(&str, usize)] = &[
+    (
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        4,
+    ),
+    (
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        4,
+    ),
+    ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 5),
+];
+
+fn perft(position: &mut Position, depth: usize, move_gen: impl GenerateMoves + Copy) -> usize {
+    if depth == 0 {
+        return 1;
+    }
+
+    let mut nodes = 0;
+    for mve in move_gen.gen_moves(position) {
+        let undo = position.make_move(&mve).unwrap();
+        nodes += perft(position, depth - 1, move_gen);
+        position.unmake_move(&mve, undo);
+    }
+    nodes
+}
+
+fn bench_move_gen(c: &mut Criterion, name: &str, move_gen: impl GenerateMoves + Copy) {
+    let mut group = c.benchmark_group(format!("move gen/{}", name));
+    group.sample_size(10);
+
+    for (fen, depth) in PERFT_BENCHMARK_FENS_AND_DEPTHS {
+        let mut position = Position::from_fen(fen).unwrap();
+        group.bench_with_input(BenchmarkId::new("perft", fen), depth, |b, &depth| {
+            b.iter(|| perft(&mut position, depth, move_gen))
+        });
+    }
+}
+
+pub fn benchmark_move_gen(c: &mut Criterion) {
+    bench_move_gen(c, "hyperbola quintessence", HYPERBOLA_QUINTESSENCE_MOVE_GEN);
+    bench_move_gen(c, "magic bitboard", MAGIC_BITBOARD_MOVE_GEN);
+    match PextMoveGen::new() {
+        Some(move_gen) => bench_move_gen(c, "pext", move_gen),
+        None => eprintln!("skipping pext, CPU doesn't support BMI2"),
+    }
+}
+
+criterion_group!(benches, benchmark_move_gen);
+criterion_main!(benches);
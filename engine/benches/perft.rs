@@ -0,0 +1,72 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use engine::{GenerateMoves, Position, HYPERBOLA_QUINTESSENCE_MOVE_GEN};
+
+// Same positions the UCI `bench` command runs perft on
+const PERFT_BENCHMARK_FENS_AND_DEPTHS: &[(&str, usize)] = &[
+    (
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        4,
+    ),
+    (
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        4,
+    ),
+    ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 5),
+];
+
+fn copy_make_perft(
+    position: &Position,
+    depth: usize,
+    move_gen: impl GenerateMoves + Copy,
+) -> usize {
+    if depth == 0 {
+        return 1;
+    }
+
+    let mut nodes = 0;
+    for mve in move_gen.gen_moves(position) {
+        let mut move_position = position.clone();
+        move_position.make_move(&mve).unwrap();
+        nodes += copy_make_perft(&move_position, depth - 1, move_gen);
+    }
+    nodes
+}
+
+fn make_unmake_perft(
+    position: &mut Position,
+    depth: usize,
+    move_gen: impl GenerateMoves + Copy,
+) -> usize {
+    if depth == 0 {
+        return 1;
+    }
+
+    let mut nodes = 0;
+    for mve in move_gen.gen_moves(position) {
+        let undo = position.make_move(&mve).unwrap();
+        nodes += make_unmake_perft(position, depth - 1, move_gen);
+        position.unmake_move(&mve, undo);
+    }
+    nodes
+}
+
+pub fn benchmark_perft(c: &mut Criterion) {
+    let mut group = c.benchmark_group("perft");
+    group.sample_size(10);
+
+    for (fen, depth) in PERFT_BENCHMARK_FENS_AND_DEPTHS {
+        let position = Position::from_fen(fen).unwrap();
+
+        group.bench_with_input(BenchmarkId::new("copy-make", fen), depth, |b, &depth| {
+            b.iter(|| copy_make_perft(&position, depth, HYPERBOLA_QUINTESSENCE_MOVE_GEN))
+        });
+
+        let mut position = position.clone();
+        group.bench_with_input(BenchmarkId::new("make-unmake", fen), depth, |b, &depth| {
+            b.iter(|| make_unmake_perft(&mut position, depth, HYPERBOLA_QUINTESSENCE_MOVE_GEN))
+        });
+    }
+}
+
+criterion_group!(benches, benchmark_perft);
+criterion_main!(benches);
@@ -1,25 +1,34 @@
 use std::sync::{atomic::AtomicBool, Arc};
 
 use criterion::{criterion_group, criterion_main, Criterion};
-use engine::{search, Position, SearchParams, HYPERBOLA_QUINTESSENCE_MOVE_GEN, POSITION_EVALUATOR};
+use engine::{
+    search, Game, Position, SearchParams, TranspositionTable, HYPERBOLA_QUINTESSENCE_MOVE_GEN,
+    POSITION_EVALUATOR,
+};
 
 pub fn benchmark_search(c: &mut Criterion) {
     let mut group = c.benchmark_group("search");
     group.sample_size(400);
 
-    let pos = Position::start();
+    let game = Game::new(Position::start());
     let search_params = SearchParams {
         max_depth: Some(4),
         ..SearchParams::default()
     };
 
+    // Cleared every iteration, otherwise every iteration after the first would just be looking up
+    // the result of the previous one
+    let transposition_table = TranspositionTable::new(1);
+
     group.bench_function("search early game", |b| {
         b.iter(|| {
+            transposition_table.clear();
             search(
-                &pos,
+                &game,
                 &search_params,
                 HYPERBOLA_QUINTESSENCE_MOVE_GEN,
                 POSITION_EVALUATOR,
+                &transposition_table,
                 Arc::new(AtomicBool::new(false)),
             )
         })
@@ -81,24 +81,25 @@ pub(crate) enum Direction {
     DecFile = -1,
 }
 
+/// A set of squares, one bit per square with A1 as the least significant bit.
 #[derive(PartialEq, Eq, Clone, Copy, Deserialize, Serialize)]
 pub struct BitBoard(u64);
 
 impl BitBoard {
-    pub(crate) const fn empty() -> Self {
+    pub const fn empty() -> Self {
         BitBoard(0)
     }
 
-    pub(crate) const fn full() -> Self {
+    pub const fn full() -> Self {
         BitBoard(u64::max_value())
     }
 
-    pub(crate) const fn from_square(square: Square) -> Self {
+    pub const fn from_square(square: Square) -> Self {
         BitBoard(1 << (square as u8))
     }
 
     // TODO: convert to From<&[Square]>
-    pub(crate) const fn from_squares(squares: &[Square]) -> Self {
+    pub const fn from_squares(squares: &[Square]) -> Self {
         let mut bb = BitBoard::empty();
         let mut sq_idx = 0;
         while sq_idx < squares.len() {
@@ -110,7 +111,7 @@ impl BitBoard {
         bb
     }
 
-    pub(crate) const fn from_val(val: u64) -> Self {
+    pub const fn from_val(val: u64) -> Self {
         BitBoard(val)
     }
 
@@ -188,11 +189,11 @@ impl BitBoard {
         ray.const_bit_and(end_bb.const_bit_not())
     }
 
-    pub(crate) fn to_val(self) -> u64 {
+    pub fn to_val(self) -> u64 {
         self.0
     }
 
-    pub(crate) fn to_squares(mut self) -> ArrayVec<Square, 32> {
+    pub fn to_squares(mut self) -> ArrayVec<Square, 64> {
         let mut sqs = ArrayVec::new();
         while !self.is_empty() {
             let sq = self.pop_lsb();
@@ -206,19 +207,19 @@ impl BitBoard {
         self.set_square(dest);
     }
 
-    pub(crate) const fn set_square(&mut self, square: Square) {
+    pub const fn set_square(&mut self, square: Square) {
         self.0 |= 1 << square as u64
     }
 
-    pub(crate) fn clear_square(&mut self, square: Square) {
+    pub fn clear_square(&mut self, square: Square) {
         self.0 &= !(1 << square as u64)
     }
 
-    pub(crate) fn is_square_set(&self, square: Square) -> bool {
+    pub fn is_square_set(&self, square: Square) -> bool {
         self.0 & 1 << (square as u64) != 0
     }
 
-    pub(crate) const fn is_empty(self) -> bool {
+    pub const fn is_empty(self) -> bool {
         self.0 == 0
     }
 
@@ -238,13 +239,13 @@ impl BitBoard {
         }
     }
 
-    pub(crate) fn get_lsb(&self) -> Square {
+    pub fn get_lsb(&self) -> Square {
         debug_assert!(self.0 != 0, "want != 0, got 0");
         let idx: u8 = self.0.trailing_zeros().try_into().unwrap();
         Square::from_repr(idx).unwrap()
     }
 
-    pub(crate) fn pop_lsb(&mut self) -> Square {
+    pub fn pop_lsb(&mut self) -> Square {
         let lsb = self.get_lsb();
         self.0 &= self.0 - 1;
         lsb
@@ -254,7 +255,7 @@ impl BitBoard {
         BitBoard(self.0.swap_bytes())
     }
 
-    pub(crate) fn num_squares_set(mut self) -> u8 {
+    pub fn num_squares_set(mut self) -> u8 {
         let mut count = 0;
 
         while self.0 != 0 {
@@ -393,6 +394,15 @@ mod tests {
         assert_eq!(format!("{:?}", got), want);
     }
 
+    #[test]
+    fn test_to_squares_full_board() {
+        let got = BitBoard::from_val(u64::MAX).to_squares();
+        assert_eq!(
+            got.as_slice(),
+            Square::iter().collect::<Vec<_>>().as_slice()
+        );
+    }
+
     #[test_case([B8, G6, A4, F1] ; "first")]
     fn test_is_piece_at(piece_squares: [Square; 4]) {
         let all_other_squares: Vec<Square> = Square::iter()
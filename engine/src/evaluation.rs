@@ -1,115 +1,134 @@
-use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::fmt::Display;
 use std::ops::Neg;
 
+use serde::{Deserialize, Serialize};
 use strum::IntoEnumIterator;
 
 use crate::bitboard::Square;
 use crate::position::{Piece, Position, Side};
 use crate::GenerateMoves;
 
-/// An evaluation of a position. Is always from the side to move's perspective.
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum Eval {
-    Score(f64),
-    Mate(u8),
-    Draw,
-}
+/// Score of a mate on the board. Mates further away score less, by a point per ply.
+const MATE_SCORE: i32 = 30_000;
+/// Mates can be at most this many plies away, which leaves the scores below for centipawns.
+const MAX_MATE_PLIES: i32 = 1_000;
+/// Largest centipawn score, so a huge material advantage can't be mistaken for a mate.
+const MAX_CENTIPAWNS: i32 = MATE_SCORE - MAX_MATE_PLIES - 1;
+
+/// An evaluation of a position in centipawns. Is always from the side to move's perspective.
+///
+/// Mates are scored beyond any centipawn score, closer to zero the further away they are, so
+/// comparing evals prefers faster mates and slower losses. Draws score 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+pub struct Eval(i32);
 
 impl Display for Eval {
+    /// In the format of UCI's `info score`, with mates in moves, negative if the side to move is
+    /// getting mated.
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Eval::Score(score) => writeln!(f, "cp {}", score / 100.),
-            Eval::Mate(plies) => {
-                let sign = if *plies > 0 { '+' } else { '-' };
-                writeln!(f, "{}M{}", sign, plies)
-            }
-            Eval::Draw => writeln!(f, "cp 0"),
+        match self.mate_moves() {
+            Some(moves) => write!(f, "mate {}", moves),
+            None => write!(f, "cp {}", self.0),
         }
     }
 }
 
-impl PartialOrd for Eval {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        match (self, other) {
-            (Eval::Score(s1), Eval::Score(s2)) => s1.partial_cmp(s2),
-            (Eval::Mate(m1), Eval::Mate(m2)) => match (*m1 % 2, *m2 % 2) {
-                (1, 0) => Some(Ordering::Greater),
-                (0, 1) => Some(Ordering::Less),
-                (0, 0) => m1.partial_cmp(m2),
-                (1, 1) => m2.partial_cmp(m1),
-                (_, _) => unreachable!(),
-            },
-            (Eval::Draw, Eval::Draw) => Some(Ordering::Equal),
-
-            (Eval::Score(s), Eval::Draw) => {
-                if *s >= 0. {
-                    Some(Ordering::Greater)
-                } else {
-                    Some(Ordering::Less)
-                }
-            }
-            (Eval::Draw, Eval::Score(s)) => {
-                if *s >= 0. {
-                    Some(Ordering::Less)
-                } else {
-                    Some(Ordering::Greater)
-                }
-            }
-            (Eval::Mate(m), Eval::Draw) => {
-                if *m % 2 == 1 {
-                    Some(Ordering::Greater)
-                } else {
-                    Some(Ordering::Less)
-                }
-            }
-            (Eval::Draw, Eval::Mate(m)) => {
-                if *m % 2 == 1 {
-                    Some(Ordering::Less)
-                } else {
-                    Some(Ordering::Greater)
-                }
-            }
-
-            (Eval::Mate(m), Eval::Score(_)) => {
-                if *m % 2 == 1 {
-                    Some(Ordering::Greater)
-                } else {
-                    Some(Ordering::Less)
-                }
-            }
-            (Eval::Score(_), Eval::Mate(m)) => {
-                if *m % 2 == 1 {
-                    Some(Ordering::Less)
-                } else {
-                    Some(Ordering::Greater)
-                }
-            }
-        }
+impl Neg for Eval {
+    type Output = Self;
+    fn neg(self) -> Self::Output {
+        self.flip()
     }
 }
 
 impl Eval {
-    pub fn flip(&self) -> Eval {
-        match self {
-            Eval::Score(score) => Eval::Score(-score),
-            Eval::Mate(mate) => Eval::Mate(mate + 1),
-            Eval::Draw => Eval::Draw,
+    pub const DRAW: Eval = Eval(0);
+    /// Better than any eval, for search windows nothing can fall outside of.
+    pub const INFINITY: Eval = Eval(MATE_SCORE + 1);
+
+    /// Centipawns are clamped to below mate scores.
+    pub fn cp(centipawns: i32) -> Eval {
+        Eval(centipawns.clamp(-MAX_CENTIPAWNS, MAX_CENTIPAWNS))
+    }
+
+    /// The side to move mates in `plies` plies.
+    pub const fn mate_in(plies: u16) -> Eval {
+        Eval(MATE_SCORE - plies as i32)
+    }
+
+    /// The side to move gets mated in `plies` plies, or is already mated with 0.
+    pub const fn mated_in(plies: u16) -> Eval {
+        Eval(-MATE_SCORE + plies as i32)
+    }
+
+    pub const fn flip(self) -> Eval {
+        Eval(-self.0)
+    }
+
+    pub fn is_mate(self) -> bool {
+        (MATE_SCORE - MAX_MATE_PLIES..=MATE_SCORE).contains(&self.0.abs())
+    }
+
+    /// `None` for mates.
+    pub fn centipawns(self) -> Option<i32> {
+        (!self.is_mate()).then_some(self.0)
+    }
+
+    /// Plies until mate, negative if the side to move is getting mated. `None` if this isn't a
+    /// mate.
+    pub fn mate_plies(self) -> Option<i32> {
+        self.is_mate()
+            .then(|| self.0.signum() * (MATE_SCORE - self.0.abs()))
+    }
+
+    /// Full moves until mate, counting the side to move's moves, negative if the side to move is
+    /// getting mated. `None` if this isn't a mate.
+    pub fn mate_moves(self) -> Option<i32> {
+        self.mate_plies().map(|plies| {
+            if plies > 0 {
+                (plies + 1) / 2
+            } else {
+                plies / 2
+            }
+        })
+    }
+
+    // The search scores mates by their distance from the root, so they can be compared across the
+    // whole tree. The transposition table can't, since a position can be reached at different
+    // plies, so it stores their distance from the position itself instead.
+
+    /// Converts a mate found `ply` plies from the root to its distance from the position itself.
+    pub(crate) fn to_node_relative(self, ply: u64) -> Eval {
+        self.shift_mate(ply as i32)
+    }
+
+    /// Converts a mate `ply` plies from the root from its distance from the position itself to
+    /// its distance from the root.
+    pub(crate) fn to_root_relative(self, ply: u64) -> Eval {
+        self.shift_mate(-(ply as i32))
+    }
+
+    fn shift_mate(self, plies: i32) -> Eval {
+        // By the sign of the score rather than of the mate plies, which are 0 for both mate in 0
+        // and mated in 0
+        if !self.is_mate() {
+            self
+        } else if self.0 > 0 {
+            Eval(self.0 + plies)
+        } else {
+            Eval(self.0 - plies)
         }
     }
-}
 
-//impl Neg for Eval {
-//    type Output = Self;
-//    fn neg(self) -> Self::Output {
-//        match self {
-//            Eval::Score(score) => Eval::Score(-score),
-//            Eval::Mate(mate) => Eval::Mate(-mate),
-//            Eval::Draw => Eval::Draw,
-//        }
-//    }
-//}
+    /// Every eval fits in 16 bits, which is what the transposition table packs it into.
+    pub(crate) const fn to_bits(self) -> u16 {
+        self.0 as i16 as u16
+    }
+
+    pub(crate) const fn from_bits(bits: u16) -> Eval {
+        Eval(bits as i16 as i32)
+    }
+}
 
 pub trait EvaluatePosition {
     fn evaluate(&self, position: &Position, move_gen: impl GenerateMoves) -> Eval;
@@ -120,102 +139,100 @@ pub struct PositionEvaluator;
 
 // Source: https://www.chessprogramming.org/Simplified_Evaluation_Function
 #[inline(always)]
-fn get_piece_square_bonus(piece: Piece, square: Square, is_early_or_mid_game: bool) -> f64 {
+fn get_piece_square_bonus(piece: Piece, square: Square, is_early_or_mid_game: bool) -> i32 {
     #[rustfmt::skip]
     let table = match (piece, is_early_or_mid_game) {
         (Piece::Pawn, ..) => [
-            0.,  0.,  0.,  0.,  0.,  0.,  0.,  0.,
-             5., 10., 10.,-20.,-20., 10., 10.,  5.,
-             5., -5.,-10.,  0.,  0.,-10., -5.,  5.,
-             0.,  0.,  0., 20., 20.,  0.,  0.,  0.,
-             5.,  5., 10., 25., 25., 10.,  5.,  5.,
-            10., 10., 20., 30., 30., 20., 10., 10.,
-            50., 50., 50., 50., 50., 50., 50., 50.,
-             0.,  0.,  0.,  0.,  0.,  0.,  0.,  0.
+            0,  0,  0,  0,  0,  0,  0,  0,
+             5, 10, 10,-20,-20, 10, 10,  5,
+             5, -5,-10,  0,  0,-10, -5,  5,
+             0,  0,  0, 20, 20,  0,  0,  0,
+             5,  5, 10, 25, 25, 10,  5,  5,
+            10, 10, 20, 30, 30, 20, 10, 10,
+            50, 50, 50, 50, 50, 50, 50, 50,
+             0,  0,  0,  0,  0,  0,  0,  0
         ],
         (Piece::King, true) => [
-            -30.,-40.,-40.,-50.,-50.,-40.,-40.,-30.,
-            -30.,-40.,-40.,-50.,-50.,-40.,-40.,-30.,
-            -30.,-40.,-40.,-50.,-50.,-40.,-40.,-30.,
-            -30.,-40.,-40.,-50.,-50.,-40.,-40.,-30.,
-            -20.,-30.,-30.,-40.,-40.,-30.,-30.,-20.,
-            -10.,-20.,-20.,-20.,-20.,-20.,-20.,-10.,
-             20., 20.,  0.,  0.,  0.,  0., 20., 20.,
-             20., 30., 10.,  0.,  0., 10., 30., 20.
+            -30,-40,-40,-50,-50,-40,-40,-30,
+            -30,-40,-40,-50,-50,-40,-40,-30,
+            -30,-40,-40,-50,-50,-40,-40,-30,
+            -30,-40,-40,-50,-50,-40,-40,-30,
+            -20,-30,-30,-40,-40,-30,-30,-20,
+            -10,-20,-20,-20,-20,-20,-20,-10,
+             20, 20,  0,  0,  0,  0, 20, 20,
+             20, 30, 10,  0,  0, 10, 30, 20
         ],
         (Piece::King, false) => [
-            -50.,-40.,-30.,-20.,-20.,-30.,-40.,-50.,
-            -30.,-20.,-10.,  0.,  0.,-10.,-20.,-30.,
-            -30.,-10., 20., 30., 30., 20.,-10.,-30.,
-            -30.,-10., 30., 40., 40., 30.,-10.,-30.,
-            -30.,-10., 30., 40., 40., 30.,-10.,-30.,
-            -30.,-10., 20., 30., 30., 20.,-10.,-30.,
-            -30.,-30.,  0.,  0.,  0.,  0.,-30.,-30.,
-            -50.,-30.,-30.,-30.,-30.,-30.,-30.,-50.
+            -50,-40,-30,-20,-20,-30,-40,-50,
+            -30,-20,-10,  0,  0,-10,-20,-30,
+            -30,-10, 20, 30, 30, 20,-10,-30,
+            -30,-10, 30, 40, 40, 30,-10,-30,
+            -30,-10, 30, 40, 40, 30,-10,-30,
+            -30,-10, 20, 30, 30, 20,-10,-30,
+            -30,-30,  0,  0,  0,  0,-30,-30,
+            -50,-30,-30,-30,-30,-30,-30,-50
         ],
         (Piece::Knight, ..) => [
-            -50.,-40.,-30.,-30.,-30.,-30.,-40.,-50.,
-            -40.,-20.,  0.,  0.,  0.,  0.,-20.,-40.,
-            -30.,  0., 10., 15., 15., 10.,  0.,-30.,
-            -30.,  5., 15., 20., 20., 15.,  5.,-30.,
-            -30.,  0., 15., 20., 20., 15.,  0.,-30.,
-            -30.,  5., 10., 15., 15., 10.,  5.,-30.,
-            -40.,-20.,  0.,  5.,  5.,  0.,-20.,-40.,
-            -50.,-40.,-30.,-30.,-30.,-30.,-40.,-50.
+            -50,-40,-30,-30,-30,-30,-40,-50,
+            -40,-20,  0,  0,  0,  0,-20,-40,
+            -30,  0, 10, 15, 15, 10,  0,-30,
+            -30,  5, 15, 20, 20, 15,  5,-30,
+            -30,  0, 15, 20, 20, 15,  0,-30,
+            -30,  5, 10, 15, 15, 10,  5,-30,
+            -40,-20,  0,  5,  5,  0,-20,-40,
+            -50,-40,-30,-30,-30,-30,-40,-50
         ],
         (Piece::Bishop, ..) => [
-            -20.,-10.,-10.,-10.,-10.,-10.,-10.,-20.,
-            -10.,  0.,  0.,  0.,  0.,  0.,  0.,-10.,
-            -10.,  0.,  5., 10., 10.,  5.,  0.,-10.,
-            -10.,  5.,  5., 10., 10.,  5.,  5.,-10.,
-            -10.,  0., 10., 10., 10., 10.,  0.,-10.,
-            -10., 10., 10., 10., 10., 10., 10.,-10.,
-            -10.,  5.,  0.,  0.,  0.,  0.,  5.,-10.,
-            -20.,-10.,-10.,-10.,-10.,-10.,-10.,-20.
+            -20,-10,-10,-10,-10,-10,-10,-20,
+            -10,  0,  0,  0,  0,  0,  0,-10,
+            -10,  0,  5, 10, 10,  5,  0,-10,
+            -10,  5,  5, 10, 10,  5,  5,-10,
+            -10,  0, 10, 10, 10, 10,  0,-10,
+            -10, 10, 10, 10, 10, 10, 10,-10,
+            -10,  5,  0,  0,  0,  0,  5,-10,
+            -20,-10,-10,-10,-10,-10,-10,-20
         ],
         (Piece::Rook, ..) => [
-              0.,  0.,  0.,  0.,  0.,  0.,  0.,  0.,
-              5., 10., 10., 10., 10., 10., 10.,  5.,
-             -5.,  0.,  0.,  0.,  0.,  0.,  0., -5.,
-             -5.,  0.,  0.,  0.,  0.,  0.,  0., -5.,
-             -5.,  0.,  0.,  0.,  0.,  0.,  0., -5.,
-             -5.,  0.,  0.,  0.,  0.,  0.,  0., -5.,
-             -5.,  0.,  0.,  0.,  0.,  0.,  0., -5.,
-              0.,  0.,  0.,  5.,  5.,  0.,  0.,  0.
+              0,  0,  0,  0,  0,  0,  0,  0,
+              5, 10, 10, 10, 10, 10, 10,  5,
+             -5,  0,  0,  0,  0,  0,  0, -5,
+             -5,  0,  0,  0,  0,  0,  0, -5,
+             -5,  0,  0,  0,  0,  0,  0, -5,
+             -5,  0,  0,  0,  0,  0,  0, -5,
+             -5,  0,  0,  0,  0,  0,  0, -5,
+              0,  0,  0,  5,  5,  0,  0,  0
         ],
         (Piece::Queen, ..) => [
-            -20.,-10.,-10., -5., -5.,-10.,-10.,-20.,
-            -10.,  0.,  0.,  0.,  0.,  0.,  0.,-10.,
-            -10.,  0.,  5.,  5.,  5.,  5.,  0.,-10.,
-             -5.,  0.,  5.,  5.,  5.,  5.,  0., -5.,
-              0.,  0.,  5.,  5.,  5.,  5.,  0., -5.,
-            -10.,  5.,  5.,  5.,  5.,  5.,  0.,-10.,
-            -10.,  0.,  5.,  0.,  0.,  0.,  0.,-10.,
-            -20.,-10.,-10., -5., -5.,-10.,-10.,-20.
+            -20,-10,-10, -5, -5,-10,-10,-20,
+            -10,  0,  0,  0,  0,  0,  0,-10,
+            -10,  0,  5,  5,  5,  5,  0,-10,
+             -5,  0,  5,  5,  5,  5,  0, -5,
+              0,  0,  5,  5,  5,  5,  0, -5,
+            -10,  5,  5,  5,  5,  5,  0,-10,
+            -10,  0,  5,  0,  0,  0,  0,-10,
+            -20,-10,-10, -5, -5,-10,-10,-20
         ],
     };
     table[square as usize]
 }
 
+/// Divides the score of positions where the side that's ahead doesn't have mating material
+const CANT_WIN_DIVISOR: i32 = 10;
+
 impl EvaluatePosition for PositionEvaluator {
-    fn evaluate(&self, position: &Position, move_gen: impl GenerateMoves) -> Eval {
+    fn evaluate(&self, position: &Position, _move_gen: impl GenerateMoves) -> Eval {
         // Return evaluation relative to the side to move
-        if position.state.half_move_clock == 50 {
-            return Eval::Draw;
-        }
-        if move_gen.gen_moves(position).is_empty() && !move_gen.gen_checkers(position).is_empty() {
-            if !move_gen.gen_checkers(position).is_empty() {
-                return Eval::Mate(0);
-            } else {
-                return Eval::Draw;
-            }
+        // Checkmate and stalemate need the legal moves, so they're left to the search, which
+        // generates them anyway
+        if position.is_fifty_move_draw() || position.is_insufficient_material() {
+            return Eval::DRAW;
         }
 
         let eval_score =
             position
                 .get_piece_locs()
                 .into_iter()
-                .fold(0., |acc, (piece, side, square)| {
+                .fold(0, |acc, (piece, side, square)| {
                     // For black, we need to flip index in order to use correct value
                     let square = if side == Side::Black {
                         square.flip()
@@ -230,23 +247,38 @@ impl EvaluatePosition for PositionEvaluator {
                         acc - val
                     }
                 });
+
+        // A side that's ahead but can't force mate is unlikely to win, so the score should be
+        // closer to a draw
+        let winning_side = if eval_score > 0 {
+            Side::White
+        } else {
+            Side::Black
+        };
+        let eval_score = if position.has_mating_material(winning_side) {
+            eval_score
+        } else {
+            eval_score / CANT_WIN_DIVISOR
+        };
+
         let eval_score = if position.state.to_move == Side::Black {
             -eval_score
         } else {
             eval_score
         };
-        Eval::Score(eval_score)
+        Eval::cp(eval_score)
     }
 }
 
-fn piece_value(piece: Piece) -> f64 {
+/// In centipawns. Static exchange evaluation values pieces the same way.
+pub(crate) fn piece_value(piece: Piece) -> i32 {
     match piece {
-        Piece::Pawn => 100.,
-        Piece::Knight => 320.,
-        Piece::Bishop => 330.,
-        Piece::Rook => 500.,
-        Piece::Queen => 900.,
-        Piece::King => 2000., // Don't use f64::MAX in case of overflows
+        Piece::Pawn => 100,
+        Piece::Knight => 320,
+        Piece::Bishop => 330,
+        Piece::Rook => 500,
+        Piece::Queen => 900,
+        Piece::King => 2000, // Don't use i32::MAX in case of overflows
     }
 }
 
@@ -261,44 +293,78 @@ mod tests {
     use test_case::test_case;
     use testresult::TestResult;
 
-    #[test_case(Eval::Score(10.), Eval::Score(-10.))]
-    #[test_case(Eval::Mate(1), Eval::Mate(2))]
-    #[test_case(Eval::Draw, Eval::Draw)]
+    #[test_case(Eval::cp(10), Eval::cp(-10))]
+    #[test_case(Eval::mate_in(1), Eval::mated_in(1))]
+    #[test_case(Eval::DRAW, Eval::DRAW)]
     fn test_eval_flip(eval_input: Eval, eval_want: Eval) {
         let eval_got = eval_input.flip();
 
         assert_eq!(eval_got, eval_want);
+        assert_eq!(-eval_input, eval_want);
     }
 
     #[test]
     fn test_eval_ord() {
         let evals_order_want = vec![
-            Eval::Mate(0),
-            Eval::Mate(2),
-            Eval::Score(-2.0),
-            Eval::Score(-0.5),
-            Eval::Draw,
-            Eval::Score(0.5),
-            Eval::Score(2.0),
-            Eval::Mate(3),
-            Eval::Mate(1),
+            Eval::mated_in(0),
+            Eval::mated_in(2),
+            Eval::cp(-200),
+            Eval::cp(-50),
+            Eval::DRAW,
+            Eval::cp(50),
+            Eval::cp(200),
+            Eval::mate_in(3),
+            Eval::mate_in(1),
         ];
         let evals = vec![
-            Eval::Mate(0),
-            Eval::Mate(1),
-            Eval::Mate(2),
-            Eval::Mate(3),
-            Eval::Score(-2.0),
-            Eval::Score(-0.5),
-            Eval::Draw,
-            Eval::Score(0.5),
-            Eval::Score(2.0),
+            Eval::mated_in(0),
+            Eval::mate_in(1),
+            Eval::mated_in(2),
+            Eval::mate_in(3),
+            Eval::cp(-200),
+            Eval::cp(-50),
+            Eval::DRAW,
+            Eval::cp(50),
+            Eval::cp(200),
         ];
 
         let mut sorted = evals.clone();
-        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        sorted.sort();
 
         assert_eq!(sorted, evals_order_want);
+        assert!(evals
+            .iter()
+            .all(|&eval| -Eval::INFINITY < eval && eval < Eval::INFINITY));
+        assert!(Eval::cp(i32::MAX) < Eval::mate_in(MAX_MATE_PLIES as u16));
+    }
+
+    #[test_case(Eval::cp(35), "cp 35" ; "positive score")]
+    #[test_case(Eval::cp(-120), "cp -120" ; "negative score")]
+    #[test_case(Eval::DRAW, "cp 0" ; "draw")]
+    #[test_case(Eval::mate_in(1), "mate 1" ; "mate in 1")]
+    #[test_case(Eval::mate_in(5), "mate 3" ; "mate in 5 plies")]
+    #[test_case(Eval::mated_in(2), "mate -1" ; "mated in 2 plies")]
+    #[test_case(Eval::mated_in(6), "mate -3" ; "mated in 6 plies")]
+    #[test_case(Eval::mated_in(0), "mate 0" ; "mated")]
+    fn test_eval_display(eval: Eval, want: &str) {
+        assert_eq!(eval.to_string(), want);
+    }
+
+    #[test_case(Eval::mate_in(5), 2, Eval::mate_in(3) ; "mate")]
+    #[test_case(Eval::mated_in(4), 3, Eval::mated_in(1) ; "mated")]
+    #[test_case(Eval::mated_in(3), 3, Eval::mated_in(0) ; "mated at node")]
+    #[test_case(Eval::cp(-75), 4, Eval::cp(-75) ; "score")]
+    #[test_case(Eval::DRAW, 4, Eval::DRAW ; "draw")]
+    fn test_eval_relative_to_node(root_relative: Eval, ply: u64, node_relative: Eval) {
+        assert_eq!(root_relative.to_node_relative(ply), node_relative);
+        assert_eq!(node_relative.to_root_relative(ply), root_relative);
+    }
+
+    #[test_case(Eval::cp(-75) ; "score")]
+    #[test_case(Eval::mate_in(3) ; "mate")]
+    #[test_case(Eval::INFINITY.flip() ; "negative infinity")]
+    fn test_eval_bits(eval: Eval) {
+        assert_eq!(Eval::from_bits(eval.to_bits()), eval);
     }
 
     #[test]
@@ -308,8 +374,34 @@ mod tests {
         let eval = POSITION_EVALUATOR.evaluate(&position, move_gen);
 
         // Should be at least 5 pawns better than the opponent
-        assert!(eval > Eval::Score(500.));
+        assert!(eval > Eval::cp(500));
+
+        Ok(())
+    }
+
+    #[test_case("2k5/Q7/8/8/8/8/8/7K w - - 100 80" ; "fifty move rule")]
+    #[test_case("8/8/4k3/8/8/3K4/3B4/8 w - - 0 1" ; "insufficient material")]
+    fn test_eval_draw(fen: &str) -> TestResult {
+        let position = Position::from_fen(fen)?;
+        let eval = POSITION_EVALUATOR.evaluate(&position, HYPERBOLA_QUINTESSENCE_MOVE_GEN);
+        assert_eq!(eval, Eval::DRAW);
+        Ok(())
+    }
+
+    #[test]
+    fn test_eval_scaled_without_mating_material() -> TestResult {
+        let move_gen = HYPERBOLA_QUINTESSENCE_MOVE_GEN;
+        // Up a knight, but knight and king can't mate
+        let knight_vs_pawn = Position::from_fen("8/p7/4k3/8/8/3K4/3N4/8 w - - 0 1")?;
+        // Up a rook, which can
+        let rook_vs_pawn = Position::from_fen("8/p7/4k3/8/8/3K4/3R4/8 w - - 0 1")?;
+
+        let knight_vs_pawn_eval = POSITION_EVALUATOR.evaluate(&knight_vs_pawn, move_gen);
+        let rook_vs_pawn_eval = POSITION_EVALUATOR.evaluate(&rook_vs_pawn, move_gen);
 
+        assert!(knight_vs_pawn_eval > Eval::DRAW);
+        assert!(knight_vs_pawn_eval < Eval::cp(100));
+        assert!(rook_vs_pawn_eval > Eval::cp(300));
         Ok(())
     }
 }
@@ -0,0 +1,242 @@
+use strum_macros::Display;
+
+use crate::move_gen::GenerateMoves;
+use crate::position::{Move, Position, PositionError};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Display)]
+pub enum GameStatus {
+    Ongoing,
+    /// The side to move is checkmated
+    Checkmate,
+    Stalemate,
+    FiftyMove,
+    InsufficientMaterial,
+    /// The position has occurred three times
+    Repetition,
+}
+
+impl GameStatus {
+    pub fn is_over(&self) -> bool {
+        *self != GameStatus::Ongoing
+    }
+
+    pub fn is_draw(&self) -> bool {
+        self.is_over() && *self != GameStatus::Checkmate
+    }
+}
+
+/// A position along with the keys of every position that came before it in the game, which is
+/// needed to detect repetitions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Game {
+    position: Position,
+    // Zobrist keys of the positions before `position`, oldest first
+    history: Vec<u64>,
+}
+
+impl Game {
+    pub fn new(position: Position) -> Self {
+        Self {
+            position,
+            history: Vec::new(),
+        }
+    }
+
+    pub fn position(&self) -> &Position {
+        &self.position
+    }
+
+    pub fn history(&self) -> &[u64] {
+        &self.history
+    }
+
+    pub fn make_move(&mut self, mve: &Move) -> Result<(), PositionError> {
+        let key = self.position.zobrist_key();
+        self.position.make_move(mve)?;
+        self.history.push(key);
+        Ok(())
+    }
+
+    /// Number of times the current position occurred earlier in the game.
+    pub fn repetitions(&self) -> usize {
+        count_repetitions(&self.history, &self.position)
+    }
+
+    /// The current position occurred at least once before.
+    pub fn is_twofold_repetition(&self) -> bool {
+        self.repetitions() >= 1
+    }
+
+    /// The current position occurred at least twice before, meaning the game is drawn.
+    pub fn is_threefold_repetition(&self) -> bool {
+        self.repetitions() >= 2
+    }
+
+    /// Like `Position::status`, but also checks for threefold repetition.
+    pub fn status(&self, move_gen: impl GenerateMoves) -> GameStatus {
+        match self.position.status(move_gen) {
+            GameStatus::Ongoing if self.is_threefold_repetition() => GameStatus::Repetition,
+            status => status,
+        }
+    }
+}
+
+impl Position {
+    /// Whether the game is over in this position. Repetitions can't be detected from just a
+    /// position, see `Game::status` for that.
+    pub fn status(&self, move_gen: impl GenerateMoves) -> GameStatus {
+        status_from_moves(self, &move_gen.gen_moves(self), move_gen)
+    }
+}
+
+/// Same as `Position::status`, for when the legal moves of `position` have already been
+/// generated.
+pub(crate) fn status_from_moves(
+    position: &Position,
+    moves: &[Move],
+    move_gen: impl GenerateMoves,
+) -> GameStatus {
+    if moves.is_empty() {
+        if move_gen.gen_checkers(position).is_empty() {
+            GameStatus::Stalemate
+        } else {
+            GameStatus::Checkmate
+        }
+    } else if position.is_fifty_move_draw() {
+        // Checked after checkmate, since checkmate on the move that reaches the fifty move rule
+        // still wins
+        GameStatus::FiftyMove
+    } else if position.is_insufficient_material() {
+        GameStatus::InsufficientMaterial
+    } else {
+        GameStatus::Ongoing
+    }
+}
+
+impl From<Position> for Game {
+    fn from(position: Position) -> Self {
+        Self::new(position)
+    }
+}
+
+/// Counts how many times `position` appears in `history`, the keys of the positions leading up to
+/// it.
+pub(crate) fn count_repetitions(history: &[u64], position: &Position) -> usize {
+    let key = position.zobrist_key();
+
+    // Pawn moves and captures can't be undone, so positions from before the last one can't be
+    // repeated. Those moves reset the half move clock, so it's the number of positions to check.
+    // The side to move also has to be the same, so only every other position can be a repeat.
+    history
+        .iter()
+        .rev()
+        .take(position.state.half_move_clock.into())
+        .skip(1)
+        .step_by(2)
+        .filter(|&&prev_key| prev_key == key)
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitboard::Square::*;
+    use crate::move_gen::HYPERBOLA_QUINTESSENCE_MOVE_GEN;
+    use test_case::test_case;
+    use testresult::TestResult;
+
+    fn knight_shuffle() -> [Move; 4] {
+        [
+            Move::new(G1, F3),
+            Move::new(G8, F6),
+            Move::new(F3, G1),
+            Move::new(F6, G8),
+        ]
+    }
+
+    #[test_case(0, 0 ; "no moves")]
+    #[test_case(1, 0 ; "one move")]
+    #[test_case(4, 1 ; "twofold")]
+    #[test_case(6, 1 ; "twofold after move")]
+    #[test_case(8, 2 ; "threefold")]
+    fn test_repetitions(num_moves: usize, want: usize) -> TestResult {
+        let mut game = Game::new(Position::start());
+        for mve in knight_shuffle().iter().cycle().take(num_moves) {
+            game.make_move(mve)?;
+        }
+
+        assert_eq!(game.repetitions(), want);
+        assert_eq!(game.is_twofold_repetition(), want >= 1);
+        assert_eq!(game.is_threefold_repetition(), want >= 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_repetitions_reset_by_pawn_move() -> TestResult {
+        let mut game = Game::new(Position::start());
+        for mve in knight_shuffle() {
+            game.make_move(&mve)?;
+        }
+        assert!(game.is_twofold_repetition());
+
+        // Pawn moves make earlier positions unreachable
+        for mve in [Move::new(E2, E3), Move::new(E7, E6)] {
+            game.make_move(&mve)?;
+        }
+        for mve in knight_shuffle() {
+            game.make_move(&mve)?;
+        }
+        assert_eq!(game.repetitions(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_repetition_after_double_push() -> TestResult {
+        let mut game = Game::new(Position::start());
+        game.make_move(&Move::new(E2, E4))?;
+        // The en passant target after the double push can't be captured onto, so the position is
+        // repeated once it's gone
+        for mve in [
+            Move::new(G8, F6),
+            Move::new(G1, F3),
+            Move::new(F6, G8),
+            Move::new(F3, G1),
+        ] {
+            game.make_move(&mve)?;
+        }
+        assert_eq!(game.repetitions(), 1);
+        Ok(())
+    }
+
+    #[test_case("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", GameStatus::Ongoing ; "start")]
+    #[test_case("R1k5/8/2K5/8/8/8/8/8 b - - 0 1", GameStatus::Checkmate ; "checkmate")]
+    #[test_case("k7/1R6/1K6/8/8/8/8/8 b - - 0 1", GameStatus::Stalemate ; "stalemate")]
+    #[test_case("k7/8/1K6/8/8/8/8/7R b - - 100 80", GameStatus::FiftyMove ; "fifty move rule")]
+    #[test_case("R1k5/8/2K5/8/8/8/8/8 b - - 100 80", GameStatus::Checkmate ; "checkmate on fifty move rule")]
+    #[test_case("k7/1R6/1K6/8/8/8/8/8 b - - 100 80", GameStatus::Stalemate ; "stalemate on fifty move rule")]
+    #[test_case("k7/8/1K6/8/8/8/8/7B b - - 0 1", GameStatus::InsufficientMaterial ; "insufficient material")]
+    fn test_position_status(fen: &str, want: GameStatus) -> TestResult {
+        let position = Position::from_fen(fen)?;
+        let got = position.status(HYPERBOLA_QUINTESSENCE_MOVE_GEN);
+        assert_eq!(got, want);
+        assert_eq!(got.is_over(), want != GameStatus::Ongoing);
+        Ok(())
+    }
+
+    #[test]
+    fn test_game_status_repetition() -> TestResult {
+        let mut game = Game::new(Position::start());
+        for mve in knight_shuffle().iter().cycle().take(8) {
+            assert_eq!(
+                game.status(HYPERBOLA_QUINTESSENCE_MOVE_GEN),
+                GameStatus::Ongoing
+            );
+            game.make_move(mve)?;
+        }
+        assert_eq!(
+            game.status(HYPERBOLA_QUINTESSENCE_MOVE_GEN),
+            GameStatus::Repetition
+        );
+        Ok(())
+    }
+}
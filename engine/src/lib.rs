@@ -4,6 +4,7 @@
 mod algebraic_notation;
 mod bitboard;
 mod evaluation;
+mod game;
 mod move_gen;
 mod perft;
 mod position;
@@ -15,9 +16,17 @@ pub const AUTHOR: &str = "Will Forman";
 pub const TRACING_TARGET_SEARCH: &str = "chess::search";
 
 pub use algebraic_notation::move_to_algebraic_notation;
-pub use bitboard::Square;
-pub use evaluation::{EvaluatePosition, POSITION_EVALUATOR};
-pub use move_gen::{GenerateMoves, HyperbolaQuintessenceMoveGen, HYPERBOLA_QUINTESSENCE_MOVE_GEN};
+pub use bitboard::{BitBoard, Square};
+pub use evaluation::{Eval, EvaluatePosition, POSITION_EVALUATOR};
+pub use game::{Game, GameStatus};
+pub use move_gen::{
+    GenType, GenerateAttacks, GenerateMoves, HyperbolaQuintessenceMoveGen, MagicBitboardMoveGen,
+    MoveGen, MoveList, NativeMoveGen, PextMoveGen, Pin, PinList, HYPERBOLA_QUINTESSENCE_MOVE_GEN,
+    MAGIC_BITBOARD_MOVE_GEN, MAX_MOVES,
+};
 pub use perft::{perft, perft_full, PerftDepthResult, PerftResult};
-pub use position::{Move, Piece, Position, PositionError, Side};
-pub use search::{search, SearchParams, SearchResultInfo};
+pub use position::{Move, MoveUndo, Piece, Position, PositionError, Side};
+pub use search::{
+    search, PvLine, SearchParams, SearchResultInfo, TranspositionTable,
+    DEFAULT_TRANSPOSITION_TABLE_SIZE_MB,
+};
@@ -1,9 +1,9 @@
 use std::collections::HashSet;
 
-use arrayvec::ArrayVec;
 use strum::IntoEnumIterator;
 
-use super::traits::{GenerateLeapingMoves, GenerateSlidingMoves};
+use super::traits::{GenerateLeapingMoves, GenerateSlidingMoves, Pin, PinList};
+use super::{GenType, MoveList};
 use crate::bitboard::Square::*;
 use crate::bitboard::{BitBoard, Direction, Square};
 use crate::position::{Move, Piece, Position, Side};
@@ -26,9 +26,11 @@ fn gen_king_moves(
     sliding_pieces: impl GenerateSlidingMoves,
 ) -> BitBoard {
     let mut moves = leaping_pieces.gen_king_moves(king_square);
+    // Exclude the king from the occupancy to handle kings moving away from checking sliding piece
     let king_danger_squares = gen_attacked_squares(
         position,
         side.opposite_side(),
+        occupancy & !BitBoard::from_square(king_square),
         leaping_pieces,
         sliding_pieces,
     );
@@ -83,16 +85,13 @@ fn gen_king_moves(
 // I would think this function should be faster if it's inlined. For some reason though, it seems
 // to be ~2% faster with it (not 100% sure this is big enough to be significant).
 #[inline(never)]
-fn gen_attacked_squares(
+pub(super) fn gen_attacked_squares(
     position: &Position,
     side: Side,
+    occupancy: BitBoard,
     leaping_pieces: impl GenerateLeapingMoves,
     sliding_pieces: impl GenerateSlidingMoves,
 ) -> BitBoard {
-    // Get occupancy but exclude king to handle kings moving away from checking sliding piece
-    let occupancy = (position.sides.get(Side::White) | position.sides.get(Side::Black))
-        & !position.pieces.get(Piece::King).get(side.opposite_side());
-
     let mut attacked_squares = BitBoard::empty();
 
     for piece_type in Piece::iter() {
@@ -141,8 +140,9 @@ fn get_pin_rays(
         let mut moves = sliding_pieces.gen_moves(Piece::Rook, pinner_square, pinner_occupancy);
         moves.set_square(pinner_square); // Want to include capturing pinner in ray
         let possible_pin_ray = moves & king_ray;
-        // If there's multiple pieces in the ray, then there's no pin
-        if (possible_pin_ray & position.sides.get(side)).num_squares_set() > 1 {
+        // If there's multiple pieces in the ray, then there's no pin, and if there's none then
+        // it's a check
+        if (possible_pin_ray & position.sides.get(side)).num_squares_set() != 1 {
             continue;
         }
         rook_pin_ray |= moves & king_ray;
@@ -162,8 +162,9 @@ fn get_pin_rays(
         let mut moves = sliding_pieces.gen_moves(Piece::Bishop, pinner_square, pinner_occupancy);
         moves.set_square(pinner_square); // Want to include capturing pinner in ray
         let possible_pin_ray = moves & king_ray;
-        // If there's multiple pieces in the ray, then there's no pin
-        if (possible_pin_ray & position.sides.get(side)).num_squares_set() > 1 {
+        // If there's multiple pieces in the ray, then there's no pin, and if there's none then
+        // it's a check
+        if (possible_pin_ray & position.sides.get(side)).num_squares_set() != 1 {
             continue;
         }
         bishop_pin_ray |= moves & king_ray;
@@ -172,6 +173,26 @@ fn get_pin_rays(
     (rook_pin_ray, bishop_pin_ray)
 }
 
+fn gen_pawn_pushes(
+    square: Square,
+    side: Side,
+    occupancy: BitBoard,
+    leaping_pieces: impl GenerateLeapingMoves,
+) -> BitBoard {
+    let mut blockers = occupancy;
+    blockers.clear_square(square);
+    let pushes = leaping_pieces.gen_pawn_pushes(square, side) & !blockers;
+
+    // This ensures that if a single push is blocked, then a double push isn't possible too
+    let shift_dir = if side == Side::White {
+        Direction::IncRank
+    } else {
+        Direction::DecRank
+    };
+    blockers.shift(shift_dir);
+    pushes & !blockers
+}
+
 pub(super) fn get_checkers(
     position: &Position,
     leaping_pieces: impl GenerateLeapingMoves,
@@ -183,35 +204,304 @@ pub(super) fn get_checkers(
     let king_square = position.pieces.get(Piece::King).get(side).pop_lsb();
     let occupancy = position.sides.get(Side::White) | position.sides.get(Side::Black);
 
-    let mut checkers = BitBoard::empty();
+    attackers_to(
+        position,
+        king_square,
+        opp_side,
+        occupancy,
+        leaping_pieces,
+        sliding_pieces,
+    )
+}
 
-    for piece_type in Piece::iter() {
-        let moves = match piece_type {
-            Piece::Knight => leaping_pieces.gen_knight_moves(king_square),
+pub(super) fn attackers_to(
+    position: &Position,
+    square: Square,
+    side: Side,
+    occupancy: BitBoard,
+    leaping_pieces: impl GenerateLeapingMoves,
+    sliding_pieces: impl GenerateSlidingMoves,
+) -> BitBoard {
+    let pieces = |piece: Piece| position.pieces.get(piece).get(side);
+    let queens = pieces(Piece::Queen);
+
+    (sliding_pieces.gen_moves(Piece::Rook, square, occupancy) & (pieces(Piece::Rook) | queens))
+        | (sliding_pieces.gen_moves(Piece::Bishop, square, occupancy)
+            & (pieces(Piece::Bishop) | queens))
+        | (leaping_pieces.gen_knight_moves(square) & pieces(Piece::Knight))
+        // A pawn attacks the square if a pawn of the other side on the square would attack it
+        | (leaping_pieces.gen_pawn_atks(square, side.opposite_side()) & pieces(Piece::Pawn))
+        | (leaping_pieces.gen_king_moves(square) & pieces(Piece::King))
+}
+
+pub(super) fn get_pins(
+    position: &Position,
+    side: Side,
+    sliding_pieces: impl GenerateSlidingMoves,
+) -> PinList {
+    let opp_side = side.opposite_side();
+    let king = position.pieces.get(Piece::King).get(side);
+    let king_square = king.get_lsb();
+    let friendly_pieces = position.sides.get(side);
+    let opp_queens = position.pieces.get(Piece::Queen).get(opp_side);
+
+    let mut pins = PinList::new();
+    for (slider, possible_pinners) in [
+        (
+            Piece::Rook,
+            position.pieces.get(Piece::Rook).get(opp_side) | opp_queens,
+        ),
+        (
+            Piece::Bishop,
+            position.pieces.get(Piece::Bishop).get(opp_side) | opp_queens,
+        ),
+    ] {
+        // Looks through friendly pieces, so any of them could be pinned
+        let king_ray = sliding_pieces.gen_moves(slider, king_square, position.sides.get(opp_side));
+        let mut pinners = king_ray & possible_pinners;
+
+        while !pinners.is_empty() {
+            let pinner = pinners.pop_lsb();
+            let mut ray = sliding_pieces.gen_moves(slider, pinner, king) & king_ray;
+            ray.set_square(pinner);
+
+            let pinned = ray & friendly_pieces;
+            if pinned.num_squares_set() == 1 {
+                pins.push(Pin {
+                    pinned: pinned.get_lsb(),
+                    pinner,
+                    ray,
+                });
+            }
+        }
+    }
+    pins
+}
+
+pub(super) fn xray_attacks(
+    piece: Piece,
+    square: Square,
+    occupancy: BitBoard,
+    blockers: BitBoard,
+    sliding_pieces: impl GenerateSlidingMoves,
+) -> BitBoard {
+    let attacks = sliding_pieces.gen_moves(piece, square, occupancy);
+    let blockers = blockers & attacks;
+    attacks ^ sliding_pieces.gen_moves(piece, square, occupancy & !blockers)
+}
+
+/// Whether a move that isn't a capture or a promotion puts the opponent in check, either directly
+/// or by discovering an attack.
+fn gives_check(
+    position: &Position,
+    mve: &Move,
+    leaping_pieces: impl GenerateLeapingMoves,
+    sliding_pieces: impl GenerateSlidingMoves,
+) -> bool {
+    let side = position.state.to_move;
+    let opp_side = side.opposite_side();
+    let opp_king_square = position.pieces.get(Piece::King).get(opp_side).get_lsb();
+    let (moved_piece, _) = position.is_piece_at(mve.src).unwrap();
+
+    let mut occupancy = position.sides.get(Side::White) | position.sides.get(Side::Black);
+    occupancy.move_piece(mve.src, mve.dest);
+
+    let pieces_after_move = |piece: Piece| {
+        let mut pieces = position.pieces.get(piece).get(side);
+        if piece == moved_piece {
+            pieces.move_piece(mve.src, mve.dest);
+        }
+        pieces
+    };
+
+    let mut rooks = pieces_after_move(Piece::Rook);
+    if moved_piece == Piece::King && mve.src.abs_diff(mve.dest) == 2 {
+        let (rook_src, rook_dest) = match mve.dest {
+            G1 => (H1, F1),
+            C1 => (A1, D1),
+            G8 => (H8, F8),
+            C8 => (A8, D8),
+            _ => panic!(
+                "castling destination: want [G1, C1, G8, C8], got {}",
+                mve.dest
+            ),
+        };
+        rooks.move_piece(rook_src, rook_dest);
+        occupancy.move_piece(rook_src, rook_dest);
+    }
+    let queens = pieces_after_move(Piece::Queen);
+
+    let attackers = (sliding_pieces.gen_moves(Piece::Rook, opp_king_square, occupancy)
+        & (rooks | queens))
+        | (sliding_pieces.gen_moves(Piece::Bishop, opp_king_square, occupancy)
+            & (pieces_after_move(Piece::Bishop) | queens))
+        | (leaping_pieces.gen_knight_moves(opp_king_square) & pieces_after_move(Piece::Knight))
+        | (leaping_pieces.gen_pawn_atks(opp_king_square, opp_side)
+            & pieces_after_move(Piece::Pawn));
+
+    !attackers.is_empty()
+}
+
+fn is_castling_pseudo_legal(position: &Position, mve: &Move, occupancy: BitBoard) -> bool {
+    let castling_rights = &position.state.castling_rights;
+    let (has_right, between): (bool, &[Square]) = match (mve.src, mve.dest) {
+        (E1, G1) => (castling_rights.white_king_side, &[F1, G1]),
+        (E1, C1) => (castling_rights.white_queen_side, &[D1, C1, B1]),
+        (E8, G8) => (castling_rights.black_king_side, &[F8, G8]),
+        (E8, C8) => (castling_rights.black_queen_side, &[D8, C8, B8]),
+        _ => return false,
+    };
+    has_right
+        && between
+            .iter()
+            .all(|&square| !occupancy.is_square_set(square))
+}
+
+/// Whether `mve` moves a piece of the side to move the way that piece moves, without checking if
+/// it leaves the king in check or castles through check.
+pub(super) fn is_pseudo_legal(
+    position: &Position,
+    mve: &Move,
+    leaping_pieces: impl GenerateLeapingMoves + std::marker::Copy,
+    sliding_pieces: impl GenerateSlidingMoves,
+) -> bool {
+    let side = position.state.to_move;
+    let Some((piece, piece_side)) = position.is_piece_at(mve.src) else {
+        return false;
+    };
+    if piece_side != side {
+        return false;
+    }
+
+    let friendly_pieces = position.sides.get(side);
+    let opp_pieces = position.sides.get(side.opposite_side());
+    let occupancy = friendly_pieces | opp_pieces;
+    if friendly_pieces.is_square_set(mve.dest) {
+        return false;
+    }
+
+    if piece != Piece::Pawn {
+        let moves = match piece {
+            Piece::Knight => leaping_pieces.gen_knight_moves(mve.src),
             Piece::Bishop | Piece::Rook | Piece::Queen => {
-                sliding_pieces.gen_moves(piece_type, king_square, occupancy)
+                sliding_pieces.gen_moves(piece, mve.src, occupancy)
+            }
+            Piece::King => {
+                if is_castling_pseudo_legal(position, mve, occupancy) {
+                    return mve.promotion.is_none();
+                }
+                leaping_pieces.gen_king_moves(mve.src)
             }
-            Piece::Pawn => leaping_pieces.gen_pawn_atks(king_square, side),
-            Piece::King => BitBoard::empty(), // Pass
+            Piece::Pawn => unreachable!(),
         };
-        let pieces = position.pieces.get(piece_type).get(opp_side);
-        checkers |= moves & pieces;
+        return mve.promotion.is_none() && moves.is_square_set(mve.dest);
     }
 
-    checkers
+    let is_promotion = if side == Side::White {
+        mve.dest >= A8
+    } else {
+        mve.dest <= H1
+    };
+    let is_promotion_valid = match mve.promotion {
+        None => !is_promotion,
+        Some(Piece::Knight | Piece::Bishop | Piece::Rook | Piece::Queen) => is_promotion,
+        Some(Piece::Pawn | Piece::King) => false,
+    };
+    if !is_promotion_valid {
+        return false;
+    }
+
+    let mut capture_targets = opp_pieces;
+    if let Some(ep_target) = position.state.en_passant_target {
+        capture_targets.set_square(ep_target);
+    }
+    let moves = gen_pawn_pushes(mve.src, side, occupancy, leaping_pieces)
+        | (leaping_pieces.gen_pawn_atks(mve.src, side) & capture_targets);
+    moves.is_square_set(mve.dest)
 }
 
-pub(super) fn gen_moves(
+/// Whether `mve` is legal, without generating every move. Meant for checking a move from somewhere
+/// other than move generation, like the transposition table or a GUI.
+pub(super) fn is_legal(
     position: &Position,
+    mve: &Move,
     leaping_pieces: impl GenerateLeapingMoves + std::marker::Copy,
     sliding_pieces: impl GenerateSlidingMoves + std::marker::Copy,
-) -> ArrayVec<Move, 80> {
-    debug_assert!(position.state.half_move_clock <= 50);
-    let mut moves = ArrayVec::new();
+) -> bool {
+    if !is_pseudo_legal(position, mve, leaping_pieces, sliding_pieces) {
+        return false;
+    }
 
-    if position.state.half_move_clock == 50 {
+    let side = position.state.to_move;
+    let opp_side = side.opposite_side();
+    let occupancy = position.sides.get(Side::White) | position.sides.get(Side::Black);
+    let (piece, _) = position.is_piece_at(mve.src).unwrap();
+
+    // King moves, including castling, are already checked against attacked squares
+    if piece == Piece::King {
+        return gen_king_moves(
+            position,
+            side,
+            mve.src,
+            occupancy,
+            leaping_pieces,
+            sliding_pieces,
+        )
+        .is_square_set(mve.dest);
+    }
+
+    let mut occupancy_after_move = occupancy;
+    occupancy_after_move.move_piece(mve.src, mve.dest);
+    let mut captured = BitBoard::from_square(mve.dest);
+    if piece == Piece::Pawn && position.state.en_passant_target == Some(mve.dest) {
+        let ep_pawn_dir = if side == Side::White {
+            Direction::DecRank
+        } else {
+            Direction::IncRank
+        };
+        captured = BitBoard::from_square_shifts(mve.dest, &[&[ep_pawn_dir]]);
+        occupancy_after_move &= !captured;
+    }
+
+    let king_square = position.pieces.get(Piece::King).get(side).get_lsb();
+    let attackers = attackers_to(
+        position,
+        king_square,
+        opp_side,
+        occupancy_after_move,
+        leaping_pieces,
+        sliding_pieces,
+    );
+    (attackers & !captured).is_empty()
+}
+
+/// Which of a piece's moves belong to a stage. `capture_targets` are the squares the piece would
+/// capture on.
+fn gen_type_mask(gen_type: GenType, is_promotion: bool, capture_targets: BitBoard) -> BitBoard {
+    match gen_type {
+        GenType::All | GenType::Evasions => BitBoard::full(),
+        GenType::Captures if is_promotion => BitBoard::full(),
+        GenType::Captures => capture_targets,
+        GenType::Quiets | GenType::QuietChecks if is_promotion => BitBoard::empty(),
+        GenType::Quiets | GenType::QuietChecks => !capture_targets,
+    }
+}
+
+pub(super) fn gen_moves_of_type(
+    position: &Position,
+    leaping_pieces: impl GenerateLeapingMoves + std::marker::Copy,
+    sliding_pieces: impl GenerateSlidingMoves + std::marker::Copy,
+    gen_type: GenType,
+) -> MoveList {
+    if gen_type == GenType::QuietChecks {
+        let mut moves =
+            gen_moves_of_type(position, leaping_pieces, sliding_pieces, GenType::Quiets);
+        moves.retain(|mve| gives_check(position, mve, leaping_pieces, sliding_pieces));
         return moves;
     }
+
+    let mut moves = MoveList::new();
+
     let side = position.state.to_move;
 
     let friendly_pieces = position.sides.get(side);
@@ -222,11 +512,17 @@ pub(super) fn gen_moves(
     let checkers = get_checkers(position, leaping_pieces, sliding_pieces);
     let num_checkers = checkers.num_squares_set();
 
+    if gen_type == GenType::Evasions && num_checkers == 0 {
+        return moves;
+    }
+
     // In the case of check, what squares are allowed to be captured and blocked
     let mut capture_mask = BitBoard::full();
     let mut push_mask = BitBoard::full();
+    // Capturing a checking pawn en passant, which only pawns can do
+    let mut en_passant_capture_mask = BitBoard::empty();
 
-    let (rook_pin_ray, bishop_pin_ray) = get_pin_rays(position, side, sliding_pieces);
+    let pins = get_pins(position, side, sliding_pieces);
 
     // If the king has more than one checker, than the only legal moves are to move the king
     if num_checkers > 1 {
@@ -240,6 +536,7 @@ pub(super) fn gen_moves(
             sliding_pieces,
         );
         moves_bb &= !friendly_pieces;
+        moves_bb &= gen_type_mask(gen_type, false, opp_pieces);
         return moves_bb
             .to_squares()
             .iter()
@@ -257,7 +554,7 @@ pub(super) fn gen_moves(
             };
             let ep_src_bb = BitBoard::from_square_shifts(ep_target, &[&[ep_dir]]);
             if ep_src_bb == checkers {
-                capture_mask |= BitBoard::from_square(ep_target);
+                en_passant_capture_mask = BitBoard::from_square(ep_target);
             }
         }
 
@@ -289,20 +586,7 @@ pub(super) fn gen_moves(
                     sliding_pieces.gen_moves(piece_type, piece_square, occupancy)
                 }
                 Piece::Pawn => {
-                    let mut pushes = leaping_pieces.gen_pawn_pushes(piece_square, side);
-                    pushes &= !opp_pieces; // Can't push into opposing piece
-
-                    // This ensures that if a single push is blocked, then a double push isn't
-                    // possible too
-                    let mut all_pieces_except_self = opp_pieces | friendly_pieces;
-                    all_pieces_except_self.clear_square(piece_square);
-                    let shift_dir = if side == Side::White {
-                        Direction::IncRank
-                    } else {
-                        Direction::DecRank
-                    };
-                    all_pieces_except_self.shift(shift_dir);
-                    pushes &= !all_pieces_except_self;
+                    let pushes = gen_pawn_pushes(piece_square, side, occupancy, leaping_pieces);
 
                     let mut possible_atks = opp_pieces;
                     if let Some(ep_target) = position.state.en_passant_target {
@@ -334,44 +618,49 @@ pub(super) fn gen_moves(
             moves_bb &= !friendly_pieces; // Don't let capture pieces on their own team
 
             // If in check, make sure only capturing moves or blocking moves
-            if piece_type != Piece::King {
-                moves_bb &= capture_mask | push_mask;
+            match piece_type {
+                Piece::King => (),
+                Piece::Pawn => moves_bb &= capture_mask | push_mask | en_passant_capture_mask,
+                _ => moves_bb &= capture_mask | push_mask,
             }
 
-            if rook_pin_ray.is_square_set(piece_square) {
-                moves_bb &= rook_pin_ray;
+            // Each pinned piece is kept to its own pin's ray. Other pins' rays aren't safe to
+            // move onto, e.g. when a piece pinned on one file could block a check along a rank.
+            if let Some(pin) = pins.iter().find(|pin| pin.pinned == piece_square) {
+                moves_bb &= pin.ray;
             }
-            if bishop_pin_ray.is_square_set(piece_square) {
-                moves_bb &= bishop_pin_ray;
+
+            let is_promotion = piece_type == Piece::Pawn
+                && ((side == Side::White && (piece_square >= A7 && piece_square <= H7))
+                    || (side == Side::Black && (piece_square >= A2 && piece_square <= H2)));
+
+            let mut capture_targets = opp_pieces;
+            if piece_type == Piece::Pawn {
+                if let Some(ep_target) = position.state.en_passant_target {
+                    capture_targets.set_square(ep_target);
+                }
             }
+            moves_bb &= gen_type_mask(gen_type, is_promotion, capture_targets);
 
             // For each promotion, we need to add 4 moves to the list,
             // 1 for each piece type
-            let moves_list: ArrayVec<Move, 32> = if piece_type == Piece::Pawn
-                && ((side == Side::White && (piece_square >= A7 && piece_square <= H7))
-                    || (side == Side::Black && (piece_square >= A2 && piece_square <= H2)))
-            {
-                moves_bb
-                    .to_squares()
-                    .iter()
-                    .flat_map(|&sq| {
-                        [
-                            Move::with_promotion(piece_square, sq, Piece::Knight),
-                            Move::with_promotion(piece_square, sq, Piece::Bishop),
-                            Move::with_promotion(piece_square, sq, Piece::Rook),
-                            Move::with_promotion(piece_square, sq, Piece::Queen),
-                        ]
-                    })
-                    .collect()
+            if is_promotion {
+                moves.extend(moves_bb.to_squares().iter().flat_map(|&sq| {
+                    [
+                        Move::with_promotion(piece_square, sq, Piece::Knight),
+                        Move::with_promotion(piece_square, sq, Piece::Bishop),
+                        Move::with_promotion(piece_square, sq, Piece::Rook),
+                        Move::with_promotion(piece_square, sq, Piece::Queen),
+                    ]
+                }));
             } else {
-                moves_bb
-                    .to_squares()
-                    .iter()
-                    .map(|&sq| Move::new(piece_square, sq))
-                    .collect()
-            };
-
-            moves.extend(moves_list);
+                moves.extend(
+                    moves_bb
+                        .to_squares()
+                        .iter()
+                        .map(|&sq| Move::new(piece_square, sq)),
+                );
+            }
         }
     }
 
@@ -665,13 +954,25 @@ mod tests {
         Move::new(E8, D8), Move::new(E8, F8),
         Move::new(E8, G8), Move::new(E8, C8),
     ]) ; "kiwipete move to en passant target")]
+    #[test_case(Position::from_fen("r3k2R/p1ppqpb1/bn2Q1p1/3Pb3/1P2P3/2N5/1PPBBP2/R3K3 b Qq - 0 1").unwrap(), &[], HashSet::from_iter([
+        Move::new(G7, F8), Move::new(G7, H8),
+    ]) ; "piece pinned on file can't block check on rank")]
+    #[test_case(Position::from_fen("8/8/4R3/1Ppp3r/1K3p2/6k1/4P1P1/8 w - c6 0 1").unwrap(), &[], HashSet::from_iter([
+        Move::new(B5, C6), Move::new(B4, C5), Move::new(B4, A3), Move::new(B4, A4),
+        Move::new(B4, B3), Move::new(B4, C3), Move::new(B4, A5),
+    ]) ; "only pawns can capture checking pawn en passant")]
     fn test_gen_moves(mut position: Position, start_moves: &[Move], want: HashSet<Move>) {
         for mve in start_moves {
             position.make_move(mve).unwrap();
         }
 
         println!("{:?}", position);
-        let got = gen_moves(&position, LEAPING_PIECES, HYPERBOLA_QUINTESSENCE);
+        let got = gen_moves_of_type(
+            &position,
+            LEAPING_PIECES,
+            HYPERBOLA_QUINTESSENCE,
+            GenType::All,
+        );
 
         assert_eq_collections!(got, want);
     }
@@ -681,7 +982,13 @@ mod tests {
         A2, B2, C2, D2, E2, F2, G2, H2,
         A3, B3, C3, D3, E3, F3, G3, H3]))]
     fn test_gen_attacked_squares(position: Position, side: Side, want: BitBoard) {
-        let got = gen_attacked_squares(&position, side, LEAPING_PIECES, HYPERBOLA_QUINTESSENCE);
+        let got = gen_attacked_squares(
+            &position,
+            side,
+            position.occupancy(),
+            LEAPING_PIECES,
+            HYPERBOLA_QUINTESSENCE,
+        );
 
         assert_eq!(got, want);
     }
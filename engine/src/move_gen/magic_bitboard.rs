@@ -1,102 +1,245 @@
-//use std::{cell::LazyCell, sync::LazyLock};
-//
-//use arrayvec::ArrayVec;
-//use strum::IntoEnumIterator;
-//
-//use crate::{bitboard::BitBoard, GenerateMoves, Piece, Square, HYPERBOLA_QUINTESSENCE_MOVE_GEN};
-//
-//use super::{masks::MASKS_LIST, traits::GenerateSlidingMoves};
-//
-//#[derive(Default)]
-//pub struct MagicBitboard;
-//
-//static MAGIC_BITBOARD: LazyLock<MagicBitboard> = LazyLock::new(|| MagicBitboard::default());
-//
-//fn gen_rook_moves(move_gen: &impl GenerateSlidingMoves) -> [BitBoard; 64 * 2_usize.pow(14)] {
-//    let mut rook_moves = [BitBoard::empty(); 64 * 2_usize.pow(14)];
-//
-//    for sq in Square::iter() {
-//        let blocker_boards = gen_blocker_boards(sq);
-//        for (board_idx, &board) in blocker_boards.iter().enumerate() {
-//            let moves = move_gen.gen_moves(Piece::Rook, sq, board);
-//            let move_idx = (sq as usize) * 2_usize.pow(14) + board_idx;
-//            rook_moves[move_idx] = moves;
-//        }
-//    }
-//    rook_moves
-//}
-//
-//struct MagicEntry {
-//    mask: BitBoard,
-//    magic: u64,
-//    shift: u8,
-//    offset: u32,
-//}
-//
-//// Magics from Ethereal: https://github.com/AndyGrant/Ethereal
-//
-//#[rustfmt::skip]
-//const ROOK_MAGICS: [MagicEntry; 64] = [
-//    0xA180022080400230, 0x0040100040022000, 0x0080088020001002, 0x0080080280841000,
-//    0x4200042010460008, 0x04800A0003040080, 0x0400110082041008, 0x008000A041000880,
-//    0x10138001A080C010, 0x0000804008200480, 0x00010011012000C0, 0x0022004128102200,
-//    0x000200081201200C, 0x202A001048460004, 0x0081000100420004, 0x4000800380004500,
-//    0x0000208002904001, 0x0090004040026008, 0x0208808010002001, 0x2002020020704940,
-//    0x8048010008110005, 0x6820808004002200, 0x0A80040008023011, 0x00B1460000811044,
-//    0x4204400080008EA0, 0xB002400180200184, 0x2020200080100380, 0x0010080080100080,
-//    0x2204080080800400, 0x0000A40080360080, 0x02040604002810B1, 0x008C218600004104,
-//    0x8180004000402000, 0x488C402000401001, 0x4018A00080801004, 0x1230002105001008,
-//    0x8904800800800400, 0x0042000C42003810, 0x008408110400B012, 0x0018086182000401,
-//    0x2240088020C28000, 0x001001201040C004, 0x0A02008010420020, 0x0010003009010060,
-//    0x0004008008008014, 0x0080020004008080, 0x0282020001008080, 0x50000181204A0004,
-//    0x48FFFE99FECFAA00, 0x48FFFE99FECFAA00, 0x497FFFADFF9C2E00, 0x613FFFDDFFCE9200,
-//    0xFFFFFFE9FFE7CE00, 0xFFFFFFF5FFF3E600, 0x0010301802830400, 0x510FFFF5F63C96A0,
-//    0xEBFFFFB9FF9FC526, 0x61FFFEDDFEEDAEAE, 0x53BFFFEDFFDEB1A2, 0x127FFFB9FFDFB5F6,
-//    0x411FFFDDFFDBF4D6, 0x0801000804000603, 0x0003FFEF27EEBE74, 0x7645FFFECBFEA79E,
-//];
-//
-//#[rustfmt::skip]
-//const BISHOP_MAGICS: [MagicEntry; 64] = [
-//    0xFFEDF9FD7CFCFFFF, 0xFC0962854A77F576, 0x5822022042000000, 0x2CA804A100200020,
-//    0x0204042200000900, 0x2002121024000002, 0xFC0A66C64A7EF576, 0x7FFDFDFCBD79FFFF,
-//    0xFC0846A64A34FFF6, 0xFC087A874A3CF7F6, 0x1001080204002100, 0x1810080489021800,
-//    0x0062040420010A00, 0x5028043004300020, 0xFC0864AE59B4FF76, 0x3C0860AF4B35FF76,
-//    0x73C01AF56CF4CFFB, 0x41A01CFAD64AAFFC, 0x040C0422080A0598, 0x4228020082004050,
-//    0x0200800400E00100, 0x020B001230021040, 0x7C0C028F5B34FF76, 0xFC0A028E5AB4DF76,
-//    0x0020208050A42180, 0x001004804B280200, 0x2048020024040010, 0x0102C04004010200,
-//    0x020408204C002010, 0x02411100020080C1, 0x102A008084042100, 0x0941030000A09846,
-//    0x0244100800400200, 0x4000901010080696, 0x0000280404180020, 0x0800042008240100,
-//    0x0220008400088020, 0x04020182000904C9, 0x0023010400020600, 0x0041040020110302,
-//    0xDCEFD9B54BFCC09F, 0xF95FFA765AFD602B, 0x1401210240484800, 0x0022244208010080,
-//    0x1105040104000210, 0x2040088800C40081, 0x43FF9A5CF4CA0C01, 0x4BFFCD8E7C587601,
-//    0xFC0FF2865334F576, 0xFC0BF6CE5924F576, 0x80000B0401040402, 0x0020004821880A00,
-//    0x8200002022440100, 0x0009431801010068, 0xC3FFB7DC36CA8C89, 0xC3FF8A54F4CA2C89,
-//    0xFFFFFCFCFD79EDFF, 0xFC0863FCCB147576, 0x040C000022013020, 0x2000104000420600,
-//    0x0400000260142410, 0x0800633408100500, 0xFC087E8E4BB2F736, 0x43FF9E4EF4CA2C89,
-//];
-//
-//const ROOK_MOVES: &[BitBoard] = todo!();
-//const BISHOP_MOVES: &[BitBoard] = todo!();
-//
-//const fn magic_index(entry: &MagicEntry, blockers: BitBoard) -> usize {
-//    let blockers = blockers & entry.mask;
-//    let hash = blockers.to_val().wrapping_mul(entry.magic);
-//    let index = (hash >> entry.shift) as usize;
-//    entry.offset as usize + index
-//}
-//
-//const fn get_rook_moves(square: Square, blockers: BitBoard) -> BitBoard {
-//    let magic = &ROOK_MAGICS[square as usize];
-//    ROOK_MOVES[magic_index(magic, blockers)]
-//}
-//
-//const fn get_bishop_moves(square: Square, blockers: BitBoard) -> BitBoard {
-//    let magic = &BISHOP_MAGICS[square as usize];
-//    BISHOP_MOVES[magic_index(magic, blockers)]
-//}
-//
-//impl GenerateSlidingMoves for MagicBitboard {
-//    fn gen_moves(&self, piece: Piece, square: Square, occupancy: BitBoard) -> BitBoard {
-//        BitBoard::empty()
-//    }
-//}
+use std::sync::LazyLock;
+
+use strum::IntoEnumIterator;
+
+use crate::bitboard::{BitBoard, Square};
+use crate::position::Piece;
+
+use super::hyperbola_quintessence::HYPERBOLA_QUINTESSENCE;
+use super::masks::{MaskType, MASKS_LIST};
+use super::traits::GenerateSlidingMoves;
+
+// Source: https://www.chessprogramming.org/Magic_Bitboards
+
+#[derive(Clone, Copy)]
+pub struct MagicBitboard;
+
+#[derive(Clone, Copy, Debug)]
+struct MagicEntry {
+    // Squares whose occupancy affects the moves, which excludes the last square of each ray since
+    // a piece there doesn't block anything
+    mask: BitBoard,
+    magic: u64,
+    shift: u8,
+    offset: usize,
+}
+
+impl MagicEntry {
+    fn index(&self, occupancy: BitBoard) -> usize {
+        let blockers = (occupancy & self.mask).to_val();
+        self.offset + (blockers.wrapping_mul(self.magic) >> self.shift) as usize
+    }
+}
+
+struct MagicTables {
+    rook_entries: [MagicEntry; 64],
+    bishop_entries: [MagicEntry; 64],
+    moves: Vec<BitBoard>,
+}
+
+static MAGIC_TABLES: LazyLock<MagicTables> = LazyLock::new(calc_magic_tables);
+
+fn calc_magic_tables() -> MagicTables {
+    let mut moves = Vec::new();
+    let rook_entries = calc_entries(Piece::Rook, &ROOK_MAGICS, &mut moves);
+    let bishop_entries = calc_entries(Piece::Bishop, &BISHOP_MAGICS, &mut moves);
+
+    MagicTables {
+        rook_entries,
+        bishop_entries,
+        moves,
+    }
+}
+
+fn calc_entries(piece: Piece, magics: &[u64; 64], moves: &mut Vec<BitBoard>) -> [MagicEntry; 64] {
+    let mut entries = [MagicEntry {
+        mask: BitBoard::empty(),
+        magic: 0,
+        shift: 0,
+        offset: 0,
+    }; 64];
+
+    for square in Square::iter() {
+        let mask = relevant_occupancy_mask(piece, square);
+        let num_bits = mask.num_squares_set();
+        let entry = MagicEntry {
+            mask,
+            magic: magics[square as usize],
+            shift: 64 - num_bits,
+            offset: moves.len(),
+        };
+
+        moves.resize(moves.len() + (1 << num_bits), BitBoard::empty());
+        let mut filled = vec![false; 1 << num_bits];
+        for blockers in blocker_subsets(mask) {
+            let idx = entry.index(blockers);
+            let square_moves = HYPERBOLA_QUINTESSENCE.gen_moves(piece, square, blockers);
+            assert!(
+                !filled[idx - entry.offset] || moves[idx] == square_moves,
+                "magic for {} on {} has a collision",
+                piece,
+                square
+            );
+            filled[idx - entry.offset] = true;
+            moves[idx] = square_moves;
+        }
+        entries[square as usize] = entry;
+    }
+    entries
+}
+
+pub(super) fn relevant_occupancy_mask(piece: Piece, square: Square) -> BitBoard {
+    let masks = MASKS_LIST.get(square);
+    match piece {
+        Piece::Rook => {
+            let file = masks.get(MaskType::File) & !(RANK_1 | RANK_8);
+            let rank = masks.get(MaskType::Rank) & !(FILE_A | FILE_H);
+            file | rank
+        }
+        Piece::Bishop => {
+            (masks.get(MaskType::Diagonal) | masks.get(MaskType::AntiDiagonal))
+                & !(RANK_1 | RANK_8 | FILE_A | FILE_H)
+        }
+        _ => panic!("piece type: want [bishop, rook], got {}", piece),
+    }
+}
+
+/// Every subset of `mask` in increasing order, using the carry-rippler trick.
+pub(super) fn blocker_subsets(mask: BitBoard) -> impl Iterator<Item = BitBoard> {
+    let mask = mask.to_val();
+    let mut subset: u64 = 0;
+    let mut done = false;
+    std::iter::from_fn(move || {
+        if done {
+            return None;
+        }
+        let curr = subset;
+        subset = subset.wrapping_sub(mask) & mask;
+        done = subset == 0;
+        Some(BitBoard::from_val(curr))
+    })
+}
+
+const RANK_1: BitBoard = BitBoard::from_val(0x0000_0000_0000_00FF);
+const RANK_8: BitBoard = BitBoard::from_val(0xFF00_0000_0000_0000);
+const FILE_A: BitBoard = BitBoard::from_val(0x0101_0101_0101_0101);
+const FILE_H: BitBoard = BitBoard::from_val(0x8080_8080_8080_8080);
+
+// Found by trying random sparse numbers until one maps every subset of the relevant occupancy
+// mask to an index without collisions, seeded so the search is reproducible. The table is
+// verified when it's built.
+#[rustfmt::skip]
+const ROOK_MAGICS: [u64; 64] = [
+    0x0080008020400018, 0x0040004020001000, 0x0700290010600040, 0x0880080080041002,
+    0x0280080004008042, 0x4500020100440008, 0x0100058C31000200, 0x010001000822C086,
+    0x0413800020804008, 0x2800402000401000, 0x2438802002801003, 0x0020808010000800,
+    0x4202000600102008, 0x205A000482001008, 0x1004000841020450, 0x0122002108441082,
+    0x0500808000400020, 0x401009402000400C, 0x8000888010002000, 0x900202000C201040,
+    0x1004110005000801, 0x2005010004000208, 0x4010040010810208, 0x80A40A0004408114,
+    0x1040005080008021, 0x0200400100210090, 0x0000200080100080, 0x8002004200201009,
+    0x1204080080040280, 0x0408040080020080, 0x100910A400021908, 0x240180028000C500,
+    0x20807B4000800180, 0xC020005000400020, 0x4040801002802000, 0x0008010010100200,
+    0x0010040082800800, 0x0204010040400200, 0x1201885044000201, 0x0102410082000044,
+    0x0020614000828000, 0x0020004010004024, 0x2420020400101000, 0x2000201001010008,
+    0x8020080004008080, 0xC202001104820008, 0x1040089019440002, 0x100010804C020021,
+    0x0001028008402900, 0x0000802000400080, 0x8000802000100080, 0x0010011308008080,
+    0x4A08080080040080, 0x0D04800400020180, 0x0000021008010400, 0x0828188C00410200,
+    0x4480488000201101, 0x0002194102002082, 0x0248102001000A43, 0x0000210004100009,
+    0x0002002018245092, 0x9002000421085082, 0x0404111200881004, 0x0010010C02224082,
+];
+
+#[rustfmt::skip]
+const BISHOP_MAGICS: [u64; 64] = [
+    0x0104011021050100, 0x0004085801002040, 0x0004010C01051040, 0x1008060241001002,
+    0x0514104400002080, 0x0011012010180000, 0x000201212010D000, 0x0400804850300800,
+    0x8020046044640080, 0x0802020404189208, 0x0800488204002000, 0x0002181481095D40,
+    0x0223040420408000, 0x0040020210044018, 0x600A090808040602, 0x8040020080880901,
+    0x5204004084544C21, 0x0220020202421209, 0x0308041000842008, 0x1004008240128000,
+    0x000C100202024010, 0x0008081101013000, 0x0422008088040200, 0x1010A14107011010,
+    0x010210002004D000, 0x8004100220418114, 0x0004010840820080, 0x80010800040A0020,
+    0x2039001201004000, 0x1030920000880405, 0x0804540001048A21, 0x00C449001200AA00,
+    0x000420A001040484, 0x0002482080A40101, 0x0854104809100880, 0x08803A0080080080,
+    0x0410020080801004, 0x2008049100080909, 0x0801024202440100, 0x001404088003A980,
+    0x8004040240860801, 0x0005E50410002010, 0x01AC802801020800, 0x040400C200901800,
+    0x0010280104C0A400, 0x0002960242000100, 0xA00408082112A043, 0x183D080208810AC8,
+    0x1080820802400444, 0x3700420804020000, 0x100A802C12080100, 0x0020104108480200,
+    0x0082011082120008, 0x4010100510042041, 0x0038A8308400C098, 0x4008880808922041,
+    0x0200440084100300, 0xE301042402021040, 0x04410900A4041211, 0x0000404000840400,
+    0x0000000004104410, 0x1001140488108104, 0x0000401084810248, 0x429084110C520200,
+];
+
+impl GenerateSlidingMoves for MagicBitboard {
+    fn gen_moves(&self, piece: Piece, square: Square, occupancy: BitBoard) -> BitBoard {
+        let tables = &*MAGIC_TABLES;
+        let rook_moves = || {
+            let entry = &tables.rook_entries[square as usize];
+            tables.moves[entry.index(occupancy)]
+        };
+        let bishop_moves = || {
+            let entry = &tables.bishop_entries[square as usize];
+            tables.moves[entry.index(occupancy)]
+        };
+
+        match piece {
+            Piece::Rook => rook_moves(),
+            Piece::Bishop => bishop_moves(),
+            Piece::Queen => rook_moves() | bishop_moves(),
+            _ => panic!("piece type: want [bishop, rook, queen], got {}", piece),
+        }
+    }
+}
+
+pub(crate) static MAGIC_BITBOARD: MagicBitboard = MagicBitboard {};
+
+#[cfg(test)]
+mod tests {
+    use super::Square::*;
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(Piece::Rook, A1, BitBoard::from_squares(&[A2, A3, A4, A5, A6, A7, B1, C1, D1, E1, F1, G1]) ; "rook corner")]
+    #[test_case(Piece::Rook, D4, BitBoard::from_squares(&[D2, D3, D5, D6, D7, B4, C4, E4, F4, G4]) ; "rook middle")]
+    #[test_case(Piece::Bishop, A1, BitBoard::from_squares(&[B2, C3, D4, E5, F6, G7]) ; "bishop corner")]
+    #[test_case(Piece::Bishop, D4, BitBoard::from_squares(&[B2, C3, E5, F6, G7, B6, C5, E3, F2]) ; "bishop middle")]
+    fn test_relevant_occupancy_mask(piece: Piece, square: Square, want: BitBoard) {
+        assert_eq!(relevant_occupancy_mask(piece, square), want);
+    }
+
+    #[test]
+    fn test_blocker_subsets() {
+        let mask = BitBoard::from_squares(&[A1, C3, H8]);
+        let subsets: Vec<BitBoard> = blocker_subsets(mask).collect();
+        assert_eq!(subsets.len(), 8);
+        assert!(subsets.iter().all(|&subset| (subset & !mask).is_empty()));
+        assert_eq!(subsets[0], BitBoard::empty());
+    }
+
+    #[test]
+    fn test_gen_moves_matches_hyperbola_quintessence() {
+        // Xorshift, so the occupancies are arbitrary but the same every run
+        let mut state: u64 = 0x2C1B_3C6D_4F4A_8E95;
+        let mut next_occupancy = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            // Fewer pieces than a full random board, which is closer to a real position
+            BitBoard::from_val(state & (state >> 17))
+        };
+
+        for _ in 0..200 {
+            let occupancy = next_occupancy();
+            for piece in [Piece::Rook, Piece::Bishop, Piece::Queen] {
+                for square in Square::iter() {
+                    assert_eq!(
+                        MAGIC_BITBOARD.gen_moves(piece, square, occupancy),
+                        HYPERBOLA_QUINTESSENCE.gen_moves(piece, square, occupancy),
+                        "{} on {} with occupancy {:?}",
+                        piece,
+                        square,
+                        occupancy
+                    );
+                }
+            }
+        }
+    }
+}
@@ -3,28 +3,471 @@ pub mod hyperbola_quintessence;
 pub mod leaping_pieces;
 pub mod magic_bitboard;
 mod masks;
+mod move_list;
+pub mod pext;
+mod see;
 mod traits;
 
-use arrayvec::ArrayVec;
+use strum_macros::Display;
 
-use crate::position::{Move, Position};
+use crate::bitboard::{BitBoard, Square};
+use crate::position::{Move, Piece, Position, Side};
 
-use self::hyperbola_quintessence::HYPERBOLA_QUINTESSENCE;
+use self::hyperbola_quintessence::{HyperbolaQuintessence, HYPERBOLA_QUINTESSENCE};
 use self::leaping_pieces::LEAPING_PIECES;
-pub use self::traits::GenerateMoves;
+use self::magic_bitboard::{MagicBitboard, MAGIC_BITBOARD};
+pub use self::move_list::{MoveList, MAX_MOVES};
+use self::pext::Pext;
+use self::traits::GenerateSlidingMoves;
+pub use self::traits::{GenerateAttacks, GenerateMoves, Pin, PinList};
 
+/// Which moves to generate, so a search can generate them lazily in stages instead of generating
+/// everything and filtering.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Display)]
+pub enum GenType {
+    /// Every legal move.
+    All,
+    /// Captures, including en passant, and every promotion.
+    Captures,
+    /// Every move that isn't in `Captures`.
+    Quiets,
+    /// Every legal move when the side to move is in check, otherwise none.
+    Evasions,
+    /// The moves in `Quiets` that put the opponent in check.
+    QuietChecks,
+}
+
+/// Generates moves with `S` for the sliding pieces, which is all that differs between the move
+/// generators.
 #[derive(Clone, Copy)]
-pub struct HyperbolaQuintessenceMoveGen;
+pub struct MoveGen<S> {
+    sliding_pieces: S,
+}
+
+impl<S: GenerateSlidingMoves + Copy> GenerateAttacks for MoveGen<S> {
+    fn attackers_to_with_occupancy(
+        &self,
+        position: &Position,
+        square: Square,
+        side: Side,
+        occupancy: BitBoard,
+    ) -> BitBoard {
+        all_pieces::attackers_to(
+            position,
+            square,
+            side,
+            occupancy,
+            LEAPING_PIECES,
+            self.sliding_pieces,
+        )
+    }
+
+    fn attacked_squares(&self, position: &Position, side: Side) -> BitBoard {
+        let occupancy = position.occupancy();
+        all_pieces::gen_attacked_squares(
+            position,
+            side,
+            occupancy,
+            LEAPING_PIECES,
+            self.sliding_pieces,
+        )
+    }
+
+    fn pins(&self, position: &Position, side: Side) -> PinList {
+        all_pieces::get_pins(position, side, self.sliding_pieces)
+    }
+
+    fn xray_attacks(
+        &self,
+        piece: Piece,
+        square: Square,
+        occupancy: BitBoard,
+        blockers: BitBoard,
+    ) -> BitBoard {
+        all_pieces::xray_attacks(piece, square, occupancy, blockers, self.sliding_pieces)
+    }
+}
+
+impl<S: GenerateSlidingMoves + Copy> GenerateMoves for MoveGen<S> {
+    fn gen_moves_of_type(&self, position: &Position, gen_type: GenType) -> MoveList {
+        all_pieces::gen_moves_of_type(position, LEAPING_PIECES, self.sliding_pieces, gen_type)
+    }
+
+    fn is_legal(&self, position: &Position, mve: &Move) -> bool {
+        all_pieces::is_legal(position, mve, LEAPING_PIECES, self.sliding_pieces)
+    }
+
+    fn is_pseudo_legal(&self, position: &Position, mve: &Move) -> bool {
+        all_pieces::is_pseudo_legal(position, mve, LEAPING_PIECES, self.sliding_pieces)
+    }
+
+    fn gen_checkers(&self, position: &Position) -> BitBoard {
+        all_pieces::get_checkers(position, LEAPING_PIECES, self.sliding_pieces)
+    }
+}
 
-impl GenerateMoves for HyperbolaQuintessenceMoveGen {
-    fn gen_moves(&self, position: &Position) -> ArrayVec<Move, 80> {
-        all_pieces::gen_moves(position, LEAPING_PIECES, HYPERBOLA_QUINTESSENCE)
+pub type HyperbolaQuintessenceMoveGen = MoveGen<HyperbolaQuintessence>;
+
+pub static HYPERBOLA_QUINTESSENCE_MOVE_GEN: HyperbolaQuintessenceMoveGen = MoveGen {
+    sliding_pieces: HYPERBOLA_QUINTESSENCE,
+};
+
+pub type MagicBitboardMoveGen = MoveGen<MagicBitboard>;
+
+pub static MAGIC_BITBOARD_MOVE_GEN: MagicBitboardMoveGen = MoveGen {
+    sliding_pieces: MAGIC_BITBOARD,
+};
+
+/// Only available on CPUs with BMI2, see `NativeMoveGen` to fall back to magic bitboards otherwise.
+pub type PextMoveGen = MoveGen<Pext>;
+
+impl PextMoveGen {
+    pub fn new() -> Option<Self> {
+        Pext::new().map(|pext| MoveGen {
+            sliding_pieces: pext,
+        })
     }
+}
 
-    fn gen_checkers(&self, position: &Position) -> crate::bitboard::BitBoard {
-        all_pieces::get_checkers(position, LEAPING_PIECES, HYPERBOLA_QUINTESSENCE)
+/// The fastest move generator the CPU supports, picked at runtime.
+#[derive(Clone, Copy, Display)]
+pub enum NativeMoveGen {
+    Pext(PextMoveGen),
+    MagicBitboard(MagicBitboardMoveGen),
+}
+
+impl NativeMoveGen {
+    pub fn detect() -> Self {
+        match PextMoveGen::new() {
+            Some(move_gen) => NativeMoveGen::Pext(move_gen),
+            None => NativeMoveGen::MagicBitboard(MAGIC_BITBOARD_MOVE_GEN),
+        }
     }
 }
 
-pub static HYPERBOLA_QUINTESSENCE_MOVE_GEN: HyperbolaQuintessenceMoveGen =
-    HyperbolaQuintessenceMoveGen {};
+impl GenerateAttacks for NativeMoveGen {
+    fn attackers_to_with_occupancy(
+        &self,
+        position: &Position,
+        square: Square,
+        side: Side,
+        occupancy: BitBoard,
+    ) -> BitBoard {
+        match self {
+            NativeMoveGen::Pext(move_gen) => {
+                move_gen.attackers_to_with_occupancy(position, square, side, occupancy)
+            }
+            NativeMoveGen::MagicBitboard(move_gen) => {
+                move_gen.attackers_to_with_occupancy(position, square, side, occupancy)
+            }
+        }
+    }
+
+    fn attacked_squares(&self, position: &Position, side: Side) -> BitBoard {
+        match self {
+            NativeMoveGen::Pext(move_gen) => move_gen.attacked_squares(position, side),
+            NativeMoveGen::MagicBitboard(move_gen) => move_gen.attacked_squares(position, side),
+        }
+    }
+
+    fn pins(&self, position: &Position, side: Side) -> PinList {
+        match self {
+            NativeMoveGen::Pext(move_gen) => move_gen.pins(position, side),
+            NativeMoveGen::MagicBitboard(move_gen) => move_gen.pins(position, side),
+        }
+    }
+
+    fn xray_attacks(
+        &self,
+        piece: Piece,
+        square: Square,
+        occupancy: BitBoard,
+        blockers: BitBoard,
+    ) -> BitBoard {
+        match self {
+            NativeMoveGen::Pext(move_gen) => {
+                move_gen.xray_attacks(piece, square, occupancy, blockers)
+            }
+            NativeMoveGen::MagicBitboard(move_gen) => {
+                move_gen.xray_attacks(piece, square, occupancy, blockers)
+            }
+        }
+    }
+}
+
+impl GenerateMoves for NativeMoveGen {
+    fn gen_moves_of_type(&self, position: &Position, gen_type: GenType) -> MoveList {
+        match self {
+            NativeMoveGen::Pext(move_gen) => move_gen.gen_moves_of_type(position, gen_type),
+            NativeMoveGen::MagicBitboard(move_gen) => {
+                move_gen.gen_moves_of_type(position, gen_type)
+            }
+        }
+    }
+
+    fn is_legal(&self, position: &Position, mve: &Move) -> bool {
+        match self {
+            NativeMoveGen::Pext(move_gen) => move_gen.is_legal(position, mve),
+            NativeMoveGen::MagicBitboard(move_gen) => move_gen.is_legal(position, mve),
+        }
+    }
+
+    fn is_pseudo_legal(&self, position: &Position, mve: &Move) -> bool {
+        match self {
+            NativeMoveGen::Pext(move_gen) => move_gen.is_pseudo_legal(position, mve),
+            NativeMoveGen::MagicBitboard(move_gen) => move_gen.is_pseudo_legal(position, mve),
+        }
+    }
+
+    fn gen_checkers(&self, position: &Position) -> BitBoard {
+        match self {
+            NativeMoveGen::Pext(move_gen) => move_gen.gen_checkers(position),
+            NativeMoveGen::MagicBitboard(move_gen) => move_gen.gen_checkers(position),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use strum::IntoEnumIterator;
+
+    use super::*;
+    use crate::bitboard::Square::*;
+    use test_case::test_case;
+    use testresult::TestResult;
+
+    /// Checks in every position of the tree that the stages split up `gen_moves` without dropping
+    /// or duplicating moves.
+    fn assert_gen_types_match_gen_moves(
+        position: &mut Position,
+        depth: usize,
+        move_gen: impl GenerateMoves + Copy,
+    ) {
+        let all: HashSet<Move> = move_gen.gen_moves(position).into_iter().collect();
+        let of_type = |gen_type| move_gen.gen_moves_of_type(position, gen_type);
+
+        let captures = of_type(GenType::Captures);
+        let quiets = of_type(GenType::Quiets);
+        let mut staged: HashSet<Move> = captures.iter().cloned().collect();
+        staged.extend(quiets.iter().cloned());
+        assert_eq!(staged, all, "captures and quiets in {}", position);
+        assert_eq!(captures.len() + quiets.len(), all.len(), "{}", position);
+
+        let evasions: HashSet<Move> = of_type(GenType::Evasions).into_iter().collect();
+        if move_gen.gen_checkers(position).is_empty() {
+            assert!(evasions.is_empty(), "evasions not in check in {}", position);
+        } else {
+            assert_eq!(evasions, all, "evasions in {}", position);
+        }
+
+        let quiet_checks: HashSet<Move> = of_type(GenType::QuietChecks).into_iter().collect();
+        let mut want_quiet_checks = HashSet::new();
+        for mve in quiets {
+            let undo = position.make_move(&mve).unwrap();
+            if !move_gen.gen_checkers(position).is_empty() {
+                want_quiet_checks.insert(mve);
+            }
+            position.unmake_move(&mve, undo);
+        }
+        assert_eq!(
+            quiet_checks, want_quiet_checks,
+            "quiet checks in {}",
+            position
+        );
+
+        if depth == 0 {
+            return;
+        }
+        for mve in all {
+            let undo = position.make_move(&mve).unwrap();
+            assert_gen_types_match_gen_moves(position, depth - 1, move_gen);
+            position.unmake_move(&mve, undo);
+        }
+    }
+
+    // The perft test positions
+    #[test_case("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", 3 ; "starting")]
+    #[test_case("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", 2 ; "kiwipete")]
+    #[test_case("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 3 ; "perft results position3")]
+    #[test_case("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", 2 ; "perft results position4")]
+    #[test_case("R6R/3Q4/1Q4Q1/4Q3/2Q4Q/Q4Q2/pp1Q4/kBNN1KB1 w - - 0 1", 0 ; "max moves 1")]
+    #[test_case("3Q4/1Q4Q1/4Q3/2Q4R/Q4Q2/3Q4/1Q4Rp/1K1BBNNk w - - 0 1", 0 ; "max moves 2")]
+    fn test_gen_types_match_gen_moves(fen: &str, depth: usize) -> TestResult {
+        let mut position = Position::from_fen(fen)?;
+
+        assert_gen_types_match_gen_moves(&mut position, depth, HYPERBOLA_QUINTESSENCE_MOVE_GEN);
+        assert_gen_types_match_gen_moves(&mut position, depth, MAGIC_BITBOARD_MOVE_GEN);
+        if let Some(move_gen) = PextMoveGen::new() {
+            assert_gen_types_match_gen_moves(&mut position, depth, move_gen);
+        }
+        Ok(())
+    }
+
+    /// Checks in every position of the tree that `is_legal` agrees with `gen_moves` for every
+    /// possible move, and that every legal move is pseudo legal.
+    fn assert_is_legal_matches_gen_moves(
+        position: &mut Position,
+        depth: usize,
+        move_gen: impl GenerateMoves + Copy,
+    ) {
+        let all = move_gen.gen_moves(position);
+        for src in Square::iter() {
+            for dest in Square::iter() {
+                let promotions = [
+                    None,
+                    Some(Piece::Knight),
+                    Some(Piece::Bishop),
+                    Some(Piece::Rook),
+                    Some(Piece::Queen),
+                ];
+                for promotion in promotions {
+                    let mve = Move {
+                        src,
+                        dest,
+                        promotion,
+                    };
+                    let is_legal = move_gen.is_legal(position, &mve);
+                    assert_eq!(is_legal, all.contains(&mve), "{:?} in {}", mve, position);
+                    assert!(
+                        !is_legal || move_gen.is_pseudo_legal(position, &mve),
+                        "{:?} in {}",
+                        mve,
+                        position
+                    );
+                }
+            }
+        }
+
+        if depth == 0 {
+            return;
+        }
+        for mve in all {
+            let undo = position.make_move(&mve).unwrap();
+            assert_is_legal_matches_gen_moves(position, depth - 1, move_gen);
+            position.unmake_move(&mve, undo);
+        }
+    }
+
+    #[test_case("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", 1 ; "starting")]
+    #[test_case("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", 1 ; "kiwipete")]
+    #[test_case("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 1 ; "perft results position3")]
+    #[test_case("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", 1 ; "perft results position4")]
+    fn test_is_legal_matches_gen_moves(fen: &str, depth: usize) -> TestResult {
+        let mut position = Position::from_fen(fen)?;
+
+        assert_is_legal_matches_gen_moves(&mut position, depth, HYPERBOLA_QUINTESSENCE_MOVE_GEN);
+        assert_is_legal_matches_gen_moves(&mut position, depth, MAGIC_BITBOARD_MOVE_GEN);
+        if let Some(move_gen) = PextMoveGen::new() {
+            assert_is_legal_matches_gen_moves(&mut position, depth, move_gen);
+        }
+        Ok(())
+    }
+
+    #[test_case("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", Move::new(B1, C3), true, true ; "knight")]
+    #[test_case("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", Move::new(B1, D2), false, false ; "onto own piece")]
+    #[test_case("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", Move::new(E7, E5), false, false ; "other side")]
+    #[test_case("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", Move::new(E4, E5), false, false ; "no piece")]
+    #[test_case("4k3/8/8/8/8/4n3/4P3/4K3 w - - 0 1", Move::new(E2, E4), false, false ; "blocked double push")]
+    #[test_case("4k3/8/8/8/4r3/8/4N3/4K3 w - - 0 1", Move::new(E2, C3), true, false ; "pinned")]
+    #[test_case("8/8/8/K2pP2r/8/8/8/7k w - d6 0 1", Move::new(E5, D6), true, false ; "en passant pinned")]
+    #[test_case("4k3/8/8/8/8/8/3r4/4K3 w - - 0 1", Move::new(E1, E2), true, false ; "king into check")]
+    #[test_case("4k3/8/8/8/8/8/3r4/4K3 w - - 0 1", Move::new(E1, D2), true, true ; "king captures")]
+    #[test_case("4k3/8/8/8/8/8/5r2/4K2R w K - 0 1", Move::new(E1, G1), true, false ; "castle through check")]
+    #[test_case("4k3/8/8/8/8/8/8/4K2R w - - 0 1", Move::new(E1, G1), false, false ; "castle without rights")]
+    #[test_case("8/P7/8/8/8/8/8/k6K w - - 0 1", Move::new(A7, A8), false, false ; "promotion without piece")]
+    #[test_case("8/P7/8/8/8/8/8/k6K w - - 0 1", Move::with_promotion(A7, A8, Piece::Knight), true, true ; "promotion")]
+    #[test_case("8/P7/8/8/8/8/8/k6K w - - 0 1", Move::with_promotion(A7, A8, Piece::King), false, false ; "promotion to king")]
+    #[test_case("8/8/8/8/8/8/8/k5RK w - - 0 1", Move::with_promotion(G1, G8, Piece::Queen), false, false ; "promotion of rook")]
+    fn test_is_legal(
+        fen: &str,
+        mve: Move,
+        want_pseudo_legal: bool,
+        want_legal: bool,
+    ) -> TestResult {
+        let position = Position::from_fen(fen)?;
+        let move_gen = MAGIC_BITBOARD_MOVE_GEN;
+
+        assert_eq!(move_gen.is_pseudo_legal(&position, &mve), want_pseudo_legal);
+        assert_eq!(move_gen.is_legal(&position, &mve), want_legal);
+        Ok(())
+    }
+
+    #[test_case("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", A8, Side::White, BitBoard::from_square(A1) ; "rook")]
+    #[test_case("4k3/8/8/8/3p4/4N3/8/4K3 w - - 0 1", E3, Side::Black, BitBoard::from_square(D4) ; "pawn")]
+    #[test_case("4k3/8/8/3n4/2P5/1B6/3R4/3QK3 w - - 0 1", D5, Side::White, BitBoard::from_squares(&[C4, D2]) ; "blocked sliders")]
+    #[test_case("4k3/8/8/3n4/8/8/8/4K3 w - - 0 1", D5, Side::White, BitBoard::empty() ; "none")]
+    fn test_attackers_to(fen: &str, square: Square, side: Side, want: BitBoard) -> TestResult {
+        let position = Position::from_fen(fen)?;
+
+        for got in [
+            HYPERBOLA_QUINTESSENCE_MOVE_GEN.attackers_to(&position, square, side),
+            MAGIC_BITBOARD_MOVE_GEN.attackers_to(&position, square, side),
+        ] {
+            assert_eq!(got, want);
+        }
+        assert_eq!(
+            MAGIC_BITBOARD_MOVE_GEN.is_attacked(&position, square, side),
+            !want.is_empty()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_attackers_to_with_occupancy_reveals_xrays() -> TestResult {
+        let position = Position::from_fen("4k3/8/8/3n4/8/8/3R4/3QK3 w - - 0 1")?;
+        let move_gen = MAGIC_BITBOARD_MOVE_GEN;
+
+        let occupancy = position.occupancy() & !BitBoard::from_square(D2);
+        let got = move_gen.attackers_to_with_occupancy(&position, D5, Side::White, occupancy);
+        assert_eq!(got, BitBoard::from_squares(&[D1, D2]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_attacked_squares() -> TestResult {
+        let position = Position::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1")?;
+
+        let got = MAGIC_BITBOARD_MOVE_GEN.attacked_squares(&position, Side::White);
+        assert_eq!(
+            got,
+            BitBoard::from_squares(&[A2, A3, A4, A5, A6, A7, A8, B1, C1, D1, D2, E2, F2, F1, E1])
+        );
+        Ok(())
+    }
+
+    #[test_case("4k3/8/8/8/4r3/8/4N3/4K3 w - - 0 1", Side::White, vec![Pin { pinned: E2, pinner: E4, ray: BitBoard::from_squares(&[E2, E3, E4]) }] ; "rook")]
+    #[test_case("4k3/8/8/b7/8/2P5/8/4K3 w - - 0 1", Side::White, vec![Pin { pinned: C3, pinner: A5, ray: BitBoard::from_squares(&[D2, C3, B4, A5]) }] ; "bishop")]
+    #[test_case("4k3/8/8/8/4r3/4N3/4N3/4K3 w - - 0 1", Side::White, vec![] ; "two pieces in the way")]
+    #[test_case("4k3/8/8/8/4r3/4n3/4N3/4K3 w - - 0 1", Side::White, vec![] ; "blocked by opponent")]
+    #[test_case("4k3/4n3/8/8/8/8/8/4Q1K1 w - - 0 1", Side::Black, vec![Pin { pinned: E7, pinner: E1, ray: BitBoard::from_squares(&[E7, E6, E5, E4, E3, E2, E1]) }] ; "black")]
+    fn test_pins(fen: &str, side: Side, want: Vec<Pin>) -> TestResult {
+        let position = Position::from_fen(fen)?;
+
+        assert_eq!(
+            HYPERBOLA_QUINTESSENCE_MOVE_GEN
+                .pins(&position, side)
+                .to_vec(),
+            want
+        );
+        assert_eq!(MAGIC_BITBOARD_MOVE_GEN.pins(&position, side).to_vec(), want);
+        let want_pinned = want.iter().fold(BitBoard::empty(), |pinned, pin| {
+            pinned | BitBoard::from_square(pin.pinned)
+        });
+        assert_eq!(
+            MAGIC_BITBOARD_MOVE_GEN.pinned_pieces(&position, side),
+            want_pinned
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_xray_attacks() {
+        let occupancy = BitBoard::from_squares(&[A1, A3, A5, C1]);
+        let blockers = BitBoard::from_squares(&[A3, C1]);
+
+        let got = MAGIC_BITBOARD_MOVE_GEN.xray_attacks(Piece::Rook, A1, occupancy, blockers);
+        assert_eq!(got, BitBoard::from_squares(&[A4, A5, D1, E1, F1, G1, H1]));
+    }
+}
@@ -0,0 +1,70 @@
+use std::ops::{Deref, DerefMut};
+
+use arrayvec::ArrayVec;
+
+use crate::position::Move;
+
+/// No legal chess position has more than 218 moves, rounded up so there's some room to spare.
+pub const MAX_MOVES: usize = 256;
+
+/// The moves generated for a position. Stored inline so generating moves never allocates.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MoveList(ArrayVec<Move, MAX_MOVES>);
+
+impl MoveList {
+    pub fn new() -> Self {
+        MoveList(ArrayVec::new())
+    }
+
+    pub fn push(&mut self, mve: Move) {
+        self.0.push(mve);
+    }
+
+    pub fn retain(&mut self, f: impl FnMut(&mut Move) -> bool) {
+        self.0.retain(f);
+    }
+}
+
+impl Deref for MoveList {
+    type Target = [Move];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for MoveList {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl IntoIterator for MoveList {
+    type Item = Move;
+    type IntoIter = arrayvec::IntoIter<Move, MAX_MOVES>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a MoveList {
+    type Item = &'a Move;
+    type IntoIter = std::slice::Iter<'a, Move>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl FromIterator<Move> for MoveList {
+    fn from_iter<I: IntoIterator<Item = Move>>(iter: I) -> Self {
+        MoveList(ArrayVec::from_iter(iter))
+    }
+}
+
+impl Extend<Move> for MoveList {
+    fn extend<I: IntoIterator<Item = Move>>(&mut self, iter: I) {
+        self.0.extend(iter);
+    }
+}
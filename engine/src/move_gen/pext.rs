@@ -0,0 +1,161 @@
+use std::sync::LazyLock;
+
+use strum::IntoEnumIterator;
+
+use crate::bitboard::{BitBoard, Square};
+use crate::position::Piece;
+
+use super::hyperbola_quintessence::HYPERBOLA_QUINTESSENCE;
+use super::magic_bitboard::{blocker_subsets, relevant_occupancy_mask};
+use super::traits::GenerateSlidingMoves;
+
+// Source: https://www.chessprogramming.org/BMI2#PEXTBitboards
+//
+// Same idea as magic bitboards, but `_pext_u64` packs the relevant blockers into a dense index
+// directly, so there's no magic multiply and no wasted table entries.
+
+/// Can only be created on a CPU that supports BMI2, which is checked at runtime.
+#[derive(Clone, Copy)]
+pub struct Pext {
+    _bmi2: (),
+}
+
+impl Pext {
+    pub(crate) fn new() -> Option<Pext> {
+        if is_bmi2_supported() {
+            Some(Pext { _bmi2: () })
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+fn is_bmi2_supported() -> bool {
+    is_x86_feature_detected!("bmi2")
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn is_bmi2_supported() -> bool {
+    false
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "bmi2")]
+fn pext(val: u64, mask: u64) -> u64 {
+    core::arch::x86_64::_pext_u64(val, mask)
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn pext(_val: u64, _mask: u64) -> u64 {
+    unreachable!("Pext can't be created without BMI2")
+}
+
+#[derive(Clone, Copy, Debug)]
+struct PextEntry {
+    mask: BitBoard,
+    offset: usize,
+}
+
+struct PextTables {
+    rook_entries: [PextEntry; 64],
+    bishop_entries: [PextEntry; 64],
+    moves: Vec<BitBoard>,
+}
+
+static PEXT_TABLES: LazyLock<PextTables> = LazyLock::new(calc_pext_tables);
+
+fn calc_pext_tables() -> PextTables {
+    let mut moves = Vec::new();
+    let rook_entries = calc_entries(Piece::Rook, &mut moves);
+    let bishop_entries = calc_entries(Piece::Bishop, &mut moves);
+
+    PextTables {
+        rook_entries,
+        bishop_entries,
+        moves,
+    }
+}
+
+fn calc_entries(piece: Piece, moves: &mut Vec<BitBoard>) -> [PextEntry; 64] {
+    let mut entries = [PextEntry {
+        mask: BitBoard::empty(),
+        offset: 0,
+    }; 64];
+
+    for square in Square::iter() {
+        let mask = relevant_occupancy_mask(piece, square);
+        entries[square as usize] = PextEntry {
+            mask,
+            offset: moves.len(),
+        };
+
+        // Subsets come in increasing order, which is the order PEXT packs them in, so the nth
+        // subset belongs at index n and the table can be built without BMI2
+        for blockers in blocker_subsets(mask) {
+            moves.push(HYPERBOLA_QUINTESSENCE.gen_moves(piece, square, blockers));
+        }
+    }
+    entries
+}
+
+impl Pext {
+    fn lookup(&self, entry: &PextEntry, occupancy: BitBoard) -> BitBoard {
+        // SAFETY: a `Pext` only exists if the CPU supports BMI2
+        let idx = unsafe { pext(occupancy.to_val(), entry.mask.to_val()) };
+        PEXT_TABLES.moves[entry.offset + idx as usize]
+    }
+}
+
+impl GenerateSlidingMoves for Pext {
+    fn gen_moves(&self, piece: Piece, square: Square, occupancy: BitBoard) -> BitBoard {
+        let tables = &*PEXT_TABLES;
+        let rook_moves = || self.lookup(&tables.rook_entries[square as usize], occupancy);
+        let bishop_moves = || self.lookup(&tables.bishop_entries[square as usize], occupancy);
+
+        match piece {
+            Piece::Rook => rook_moves(),
+            Piece::Bishop => bishop_moves(),
+            Piece::Queen => rook_moves() | bishop_moves(),
+            _ => panic!("piece type: want [bishop, rook, queen], got {}", piece),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gen_moves_matches_hyperbola_quintessence() {
+        let Some(pext) = Pext::new() else {
+            eprintln!("skipping, CPU doesn't support BMI2");
+            return;
+        };
+
+        // Xorshift, so the occupancies are arbitrary but the same every run
+        let mut state: u64 = 0x2C1B_3C6D_4F4A_8E95;
+        let mut next_occupancy = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            BitBoard::from_val(state & (state >> 17))
+        };
+
+        for _ in 0..200 {
+            let occupancy = next_occupancy();
+            for piece in [Piece::Rook, Piece::Bishop, Piece::Queen] {
+                for square in Square::iter() {
+                    assert_eq!(
+                        pext.gen_moves(piece, square, occupancy),
+                        HYPERBOLA_QUINTESSENCE.gen_moves(piece, square, occupancy),
+                        "{} on {} with occupancy {:?}",
+                        piece,
+                        square,
+                        occupancy
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,273 @@
+use strum::IntoEnumIterator;
+
+use crate::bitboard::{BitBoard, Direction, Square};
+use crate::evaluation::piece_value;
+use crate::position::{Move, Piece, Position, Side};
+
+use super::traits::GenerateAttacks;
+
+// Source: https://www.chessprogramming.org/Static_Exchange_Evaluation
+//
+// Both sides take turns capturing on the destination square with their least valuable attacker.
+// Sliders behind a piece that just captured are picked up by recomputing the attackers with it
+// removed from the occupancy. Pins are ignored.
+
+/// The state of the exchange right after `mve` is made.
+struct Exchange {
+    /// What the side that made `mve` has won so far.
+    gain: i32,
+    /// What the next capture on the destination square wins.
+    on_square: i32,
+    occupancy: BitBoard,
+    /// The side that captures next.
+    side: Side,
+}
+
+/// `None` for castling, which can't win or lose material.
+fn start_exchange(position: &Position, mve: &Move) -> Option<Exchange> {
+    let (piece, side) = position
+        .is_piece_at(mve.src)
+        .expect("move should start on a piece");
+    if piece == Piece::King && mve.src.abs_diff(mve.dest) == 2 {
+        return None;
+    }
+
+    let mut occupancy = position.occupancy();
+    occupancy.clear_square(mve.src);
+
+    let mut gain = match position.is_piece_at(mve.dest) {
+        Some((captured, _)) => piece_value(captured),
+        None if piece == Piece::Pawn && position.state.en_passant_target == Some(mve.dest) => {
+            let mut captured = BitBoard::from_square(mve.dest);
+            captured.shift(if side == Side::White {
+                Direction::DecRank
+            } else {
+                Direction::IncRank
+            });
+            occupancy ^= captured;
+            piece_value(Piece::Pawn)
+        }
+        None => 0,
+    };
+
+    let on_square = match mve.promotion {
+        Some(promotion) => {
+            gain += piece_value(promotion) - piece_value(Piece::Pawn);
+            piece_value(promotion)
+        }
+        None => piece_value(piece),
+    };
+    occupancy.set_square(mve.dest);
+
+    Some(Exchange {
+        gain,
+        on_square,
+        occupancy,
+        side: side.opposite_side(),
+    })
+}
+
+fn attackers(
+    move_gen: &(impl GenerateAttacks + ?Sized),
+    position: &Position,
+    square: Square,
+    side: Side,
+    occupancy: BitBoard,
+) -> BitBoard {
+    move_gen.attackers_to_with_occupancy(position, square, side, occupancy) & occupancy
+}
+
+fn least_valuable_attacker(
+    position: &Position,
+    attackers: BitBoard,
+    side: Side,
+) -> (Piece, Square) {
+    Piece::iter()
+        .find_map(|piece| {
+            let pieces = attackers & position.piece_occupancy(piece, side);
+            (!pieces.is_empty()).then(|| (piece, pieces.get_lsb()))
+        })
+        .expect("attackers should be pieces of side")
+}
+
+pub(super) fn see(
+    move_gen: &(impl GenerateAttacks + ?Sized),
+    position: &Position,
+    mve: &Move,
+) -> i32 {
+    let Some(Exchange {
+        gain,
+        mut on_square,
+        mut occupancy,
+        mut side,
+    }) = start_exchange(position, mve)
+    else {
+        return 0;
+    };
+
+    // Every capture can be followed by at most one more from each of the other 31 pieces
+    let mut gains = [0; 32];
+    gains[0] = gain;
+    let mut depth = 0;
+
+    loop {
+        let side_attackers = attackers(move_gen, position, mve.dest, side, occupancy);
+        if side_attackers.is_empty() {
+            break;
+        }
+        let (piece, square) = least_valuable_attacker(position, side_attackers, side);
+        occupancy.clear_square(square);
+
+        // The king can't capture onto a defended square
+        if piece == Piece::King
+            && !attackers(
+                move_gen,
+                position,
+                mve.dest,
+                side.opposite_side(),
+                occupancy,
+            )
+            .is_empty()
+        {
+            break;
+        }
+
+        depth += 1;
+        gains[depth] = on_square - gains[depth - 1];
+        on_square = piece_value(piece);
+        side = side.opposite_side();
+    }
+
+    // Each side only captures if it does better than stopping
+    while depth > 0 {
+        gains[depth - 1] = -(-gains[depth - 1]).max(gains[depth]);
+        depth -= 1;
+    }
+    gains[0]
+}
+
+pub(super) fn see_ge(
+    move_gen: &(impl GenerateAttacks + ?Sized),
+    position: &Position,
+    mve: &Move,
+    threshold: i32,
+) -> bool {
+    let Some(Exchange {
+        gain,
+        on_square,
+        mut occupancy,
+        mut side,
+    }) = start_exchange(position, mve)
+    else {
+        return 0 >= threshold;
+    };
+
+    // How far above the threshold the side that made `mve` is, from the side to capture's
+    // perspective once it's flipped
+    let mut swap = gain - threshold;
+    if swap < 0 {
+        return false;
+    }
+    swap = on_square - swap;
+    if swap <= 0 {
+        return true;
+    }
+
+    // Whether the side that made `mve` ends up at or above the threshold if the exchange stops
+    // before the side to capture's turn
+    let mut res = true;
+    loop {
+        let side_attackers = attackers(move_gen, position, mve.dest, side, occupancy);
+        if side_attackers.is_empty() {
+            break;
+        }
+        let (piece, square) = least_valuable_attacker(position, side_attackers, side);
+        occupancy.clear_square(square);
+
+        if piece == Piece::King {
+            let defended = !attackers(
+                move_gen,
+                position,
+                mve.dest,
+                side.opposite_side(),
+                occupancy,
+            )
+            .is_empty();
+            // The king can only capture if the square isn't defended
+            return if defended { res } else { !res };
+        }
+
+        res = !res;
+        swap = piece_value(piece) - swap;
+        if swap < i32::from(res) {
+            break;
+        }
+        side = side.opposite_side();
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitboard::Square::*;
+    use crate::move_gen::{GenerateMoves, MAGIC_BITBOARD_MOVE_GEN};
+    use test_case::test_case;
+    use testresult::TestResult;
+
+    #[test_case("4k3/8/8/4p3/8/8/8/4R1K1 w - - 0 1", Move::new(E1, E5), 100 ; "undefended")]
+    #[test_case("4k3/8/3p4/4p3/8/8/8/4R1K1 w - - 0 1", Move::new(E1, E5), -400 ; "defended")]
+    #[test_case("1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1", Move::new(D3, E5), -220 ; "x-rays on both sides")]
+    #[test_case("4k3/3r4/8/3p4/8/8/3R4/3RK3 w - - 0 1", Move::new(D2, D5), 100 ; "x-ray wins")]
+    #[test_case("3rk3/3r4/8/3p4/8/8/3R4/3RK3 w - - 0 1", Move::new(D2, D5), -400 ; "x-ray loses")]
+    #[test_case("2r1k3/8/8/2n5/1P6/8/8/2R1K3 w - - 0 1", Move::new(B4, C5), 320 ; "recapture not worth it")]
+    #[test_case("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", Move::new(E5, D6), 100 ; "en passant")]
+    #[test_case("4k3/P7/8/8/8/8/8/4K3 w - - 0 1", Move::with_promotion(A7, A8, Piece::Queen), 800 ; "promotion")]
+    #[test_case("4k3/P7/1n6/8/8/8/8/4K3 w - - 0 1", Move::with_promotion(A7, A8, Piece::Queen), -100 ; "defended promotion")]
+    #[test_case("8/8/8/3k4/4p3/8/2B5/4R1K1 w - - 0 1", Move::new(E1, E4), 100 ; "king can't recapture")]
+    #[test_case("8/8/8/3k4/4p3/8/8/4R1K1 w - - 0 1", Move::new(E1, E4), -400 ; "king recaptures")]
+    #[test_case("4k3/8/8/8/8/3p4/8/3QK3 w - - 0 1", Move::new(D1, C2), -900 ; "quiet onto attacked square")]
+    #[test_case("4k3/8/8/8/8/3p4/8/3QK3 w - - 0 1", Move::new(D1, D2), 0 ; "quiet onto safe square")]
+    #[test_case("4k3/8/8/8/8/8/8/4K2R w K - 0 1", Move::new(E1, G1), 0 ; "castling")]
+    #[test_case("4k3/8/8/8/4p3/5P2/6K1/8 b - - 0 1", Move::new(E4, F3), 0 ; "black pawn trade")]
+    fn test_see(fen: &str, mve: Move, want: i32) -> TestResult {
+        let position = Position::from_fen(fen)?;
+        let move_gen = MAGIC_BITBOARD_MOVE_GEN;
+
+        assert_eq!(move_gen.see(&position, &mve), want);
+        for threshold in [want - 1, want, want + 1] {
+            assert_eq!(
+                move_gen.see_ge(&position, &mve, threshold),
+                want >= threshold,
+                "threshold {}",
+                threshold
+            );
+        }
+        Ok(())
+    }
+
+    #[test_case("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1" ; "kiwipete")]
+    #[test_case("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10" ; "position 6")]
+    #[test_case("1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1" ; "x-rays")]
+    fn test_see_ge_matches_see(fen: &str) -> TestResult {
+        let position = Position::from_fen(fen)?;
+        let move_gen = MAGIC_BITBOARD_MOVE_GEN;
+
+        for mve in move_gen.gen_moves(&position) {
+            let value = move_gen.see(&position, &mve);
+            for threshold in (-1000..=1000)
+                .step_by(10)
+                .chain([value - 1, value, value + 1])
+            {
+                assert_eq!(
+                    move_gen.see_ge(&position, &mve, threshold),
+                    value >= threshold,
+                    "{:?} with threshold {}",
+                    mve,
+                    threshold
+                );
+            }
+        }
+        Ok(())
+    }
+}
@@ -3,6 +3,8 @@ use arrayvec::ArrayVec;
 use crate::bitboard::{BitBoard, Square};
 use crate::position::{Move, Piece, Position, Side};
 
+use super::{see, GenType, MoveList};
+
 pub(super) trait GenerateLeapingMoves {
     fn gen_king_moves(&self, square: Square) -> BitBoard;
     fn gen_knight_moves(&self, square: Square) -> BitBoard;
@@ -15,7 +17,87 @@ pub(super) trait GenerateSlidingMoves {
     fn gen_moves(&self, piece: Piece, square: Square, occupancy: BitBoard) -> BitBoard;
 }
 
-pub trait GenerateMoves {
-    fn gen_moves(&self, position: &Position) -> ArrayVec<Move, 80>;
+/// A piece pinned to its king, so it can only move along `ray`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pin {
+    pub pinned: Square,
+    pub pinner: Square,
+    /// The squares between the king and the pinner, and the pinner itself.
+    pub ray: BitBoard,
+}
+
+/// A piece can be pinned from at most each of the 8 directions around its king.
+pub type PinList = ArrayVec<Pin, 8>;
+
+pub trait GenerateAttacks {
+    /// Pieces of `side` that attack `square` if the occupied squares were `occupancy`. Taking
+    /// pieces out of `occupancy` reveals the sliders behind them, e.g. to find x-ray attackers.
+    fn attackers_to_with_occupancy(
+        &self,
+        position: &Position,
+        square: Square,
+        side: Side,
+        occupancy: BitBoard,
+    ) -> BitBoard;
+
+    /// Pieces of `side` that attack `square`.
+    fn attackers_to(&self, position: &Position, square: Square, side: Side) -> BitBoard {
+        self.attackers_to_with_occupancy(position, square, side, position.occupancy())
+    }
+
+    fn is_attacked(&self, position: &Position, square: Square, side: Side) -> bool {
+        !self.attackers_to(position, square, side).is_empty()
+    }
+
+    /// Every square `side` attacks.
+    fn attacked_squares(&self, position: &Position, side: Side) -> BitBoard;
+
+    /// `side`'s pieces that are pinned to their king.
+    fn pins(&self, position: &Position, side: Side) -> PinList;
+
+    fn pinned_pieces(&self, position: &Position, side: Side) -> BitBoard {
+        self.pins(position, side)
+            .iter()
+            .fold(BitBoard::empty(), |pinned, pin| {
+                pinned | BitBoard::from_square(pin.pinned)
+            })
+    }
+
+    /// The squares a sliding `piece` on `square` attacks behind the first of `blockers` it runs
+    /// into in each direction.
+    fn xray_attacks(
+        &self,
+        piece: Piece,
+        square: Square,
+        occupancy: BitBoard,
+        blockers: BitBoard,
+    ) -> BitBoard;
+
+    /// Static exchange evaluation: the material `mve` wins in centipawns if both sides keep
+    /// recapturing on its destination square with their least valuable piece for as long as it
+    /// pays off. Negative if the moved piece can be won for less than it captured.
+    fn see(&self, position: &Position, mve: &Move) -> i32 {
+        see::see(self, position, mve)
+    }
+
+    /// Whether `see` is at least `threshold`, which can stop as soon as the outcome is known.
+    fn see_ge(&self, position: &Position, mve: &Move, threshold: i32) -> bool {
+        see::see_ge(self, position, mve, threshold)
+    }
+}
+
+pub trait GenerateMoves: GenerateAttacks {
+    fn gen_moves(&self, position: &Position) -> MoveList {
+        self.gen_moves_of_type(position, GenType::All)
+    }
+
+    fn gen_moves_of_type(&self, position: &Position, gen_type: GenType) -> MoveList;
+
+    /// Whether `mve` is legal in `position`, which is cheaper than generating every move.
+    fn is_legal(&self, position: &Position, mve: &Move) -> bool;
+
+    /// Whether `mve` moves a piece of the side to move the way that piece moves, ignoring whether
+    /// it leaves the king in check.
+    fn is_pseudo_legal(&self, position: &Position, mve: &Move) -> bool;
     fn gen_checkers(&self, position: &Position) -> BitBoard;
 }
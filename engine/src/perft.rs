@@ -7,6 +7,7 @@ use std::{
 
 use tabled::{Table, Tabled};
 
+use crate::game::{status_from_moves, GameStatus};
 use crate::move_gen::{GenerateMoves, HYPERBOLA_QUINTESSENCE_MOVE_GEN};
 use crate::position::{Piece, Position};
 use crate::{bitboard::BitBoard, move_gen, Move};
@@ -79,11 +80,12 @@ pub fn perft(
     let moves = move_gen.gen_moves(position);
     let mut perft_results: HashMap<Move, usize> = HashMap::with_capacity(moves.len());
 
+    let mut position = position.clone();
     for mve in moves {
-        let mut move_pos = position.clone();
-        move_pos.make_move(&mve).unwrap();
+        let undo = position.make_move(&mve).unwrap();
+        let moves_count = perft_helper(&mut position, 1, depth, move_gen);
+        position.unmake_move(&mve, undo);
 
-        let moves_count = perft_helper(&move_pos, 1, depth, move_gen);
         perft_results.insert(mve, moves_count);
     }
 
@@ -93,7 +95,7 @@ pub fn perft(
 }
 
 fn perft_helper(
-    position: &Position,
+    position: &mut Position,
     curr_depth: usize,
     max_depth: usize,
     move_gen: impl GenerateMoves + Copy,
@@ -105,11 +107,9 @@ fn perft_helper(
     let mut moves_count = 0;
     let moves = move_gen.gen_moves(position);
     for mve in moves {
-        let mut move_pos = position.clone();
-        move_pos.make_move(&mve).unwrap();
-
-        let curr_move_moves_count = perft_helper(&move_pos, curr_depth + 1, max_depth, move_gen);
-        moves_count += curr_move_moves_count;
+        let undo = position.make_move(&mve).unwrap();
+        moves_count += perft_helper(position, curr_depth + 1, max_depth, move_gen);
+        position.unmake_move(&mve, undo);
     }
     moves_count
 }
@@ -123,7 +123,13 @@ pub fn perft_full(
 
     let start = Instant::now();
 
-    perft_full_helper(&mut depth_results, position, depth, 0, move_gen);
+    perft_full_helper(
+        &mut depth_results,
+        &mut position.clone(),
+        depth,
+        0,
+        move_gen,
+    );
 
     let time_elapsed = start.elapsed();
 
@@ -141,7 +147,7 @@ pub fn perft_full(
 
 fn perft_full_helper(
     depth_results: &mut Vec<PerftDepthResult>,
-    position: &Position,
+    position: &mut Position,
     max_depth: usize,
     curr_depth: usize,
     move_gen: impl GenerateMoves + Copy,
@@ -151,8 +157,10 @@ fn perft_full_helper(
     let moves = move_gen.gen_moves(position);
 
     if moves.is_empty() {
-        let prev_res = depth_results.get_mut(curr_depth - 1).unwrap();
-        prev_res.checkmates += 1;
+        if status_from_moves(position, &moves, move_gen) == GameStatus::Checkmate {
+            let prev_res = depth_results.get_mut(curr_depth - 1).unwrap();
+            prev_res.checkmates += 1;
+        }
         return;
     }
 
@@ -226,10 +234,9 @@ fn perft_full_helper(
     let mut tot_discovery_checks = 0;
 
     for mve in moves {
-        let mut move_position = position.clone();
-        move_position.make_move(&mve).unwrap();
+        let undo = position.make_move(&mve).unwrap();
 
-        let mut checkers = move_gen.gen_checkers(&move_position);
+        let mut checkers = move_gen.gen_checkers(position);
         if !checkers.is_empty() {
             tot_checks += 1;
             if checkers.num_squares_set() > 1 {
@@ -242,13 +249,8 @@ fn perft_full_helper(
             }
         }
 
-        perft_full_helper(
-            depth_results,
-            &move_position,
-            max_depth,
-            curr_depth + 1,
-            move_gen,
-        );
+        perft_full_helper(depth_results, position, max_depth, curr_depth + 1, move_gen);
+        position.unmake_move(&mve, undo);
     }
 
     // Reborrow to avoid multiple mutable references
@@ -261,20 +263,61 @@ fn perft_full_helper(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use arrayvec::ArrayVec;
     use test_case::test_case;
 
+    use crate::bitboard::Square;
     use crate::bitboard::Square::*;
-    use crate::position::Move;
+    use crate::move_gen::{GenType, GenerateAttacks, MoveList, PinList};
+    use crate::position::{Move, Piece, Side};
 
     #[derive(Clone, Copy)]
     struct MoveGenStub<'a> {
         moves: &'a [Move],
     }
 
+    // Attacks don't depend on the stubbed moves, so they're left to a real move generator
+    impl GenerateAttacks for MoveGenStub<'_> {
+        fn attackers_to_with_occupancy(
+            &self,
+            position: &Position,
+            square: Square,
+            side: Side,
+            occupancy: BitBoard,
+        ) -> BitBoard {
+            HYPERBOLA_QUINTESSENCE_MOVE_GEN
+                .attackers_to_with_occupancy(position, square, side, occupancy)
+        }
+
+        fn attacked_squares(&self, position: &Position, side: Side) -> BitBoard {
+            HYPERBOLA_QUINTESSENCE_MOVE_GEN.attacked_squares(position, side)
+        }
+
+        fn pins(&self, position: &Position, side: Side) -> PinList {
+            HYPERBOLA_QUINTESSENCE_MOVE_GEN.pins(position, side)
+        }
+
+        fn xray_attacks(
+            &self,
+            piece: Piece,
+            square: Square,
+            occupancy: BitBoard,
+            blockers: BitBoard,
+        ) -> BitBoard {
+            HYPERBOLA_QUINTESSENCE_MOVE_GEN.xray_attacks(piece, square, occupancy, blockers)
+        }
+    }
+
     impl GenerateMoves for MoveGenStub<'_> {
-        fn gen_moves(&self, _position: &Position) -> ArrayVec<Move, 80> {
-            ArrayVec::from_iter(self.moves.iter().cloned())
+        fn gen_moves_of_type(&self, _position: &Position, _gen_type: GenType) -> MoveList {
+            MoveList::from_iter(self.moves.iter().cloned())
+        }
+
+        fn is_legal(&self, _position: &Position, mve: &Move) -> bool {
+            self.moves.contains(mve)
+        }
+
+        fn is_pseudo_legal(&self, position: &Position, mve: &Move) -> bool {
+            self.is_legal(position, mve)
         }
 
         fn gen_checkers(&self, _position: &Position) -> BitBoard {
@@ -291,4 +334,11 @@ mod tests {
         let res = perft_full(&start_position, 1, move_gen);
         assert_eq!(res.depth_results[0].en_passants, want);
     }
+
+    #[test_case(Position::from_fen("k7/8/1K6/8/8/8/8/7R w - - 0 1").unwrap(), 1 ; "checkmate")]
+    #[test_case(Position::from_fen("k7/8/8/8/8/8/8/1Q2K3 w - - 0 1").unwrap(), 0 ; "stalemate")]
+    fn test_count_checkmates(start_position: Position, want: u64) {
+        let res = perft_full(&start_position, 1, HYPERBOLA_QUINTESSENCE_MOVE_GEN);
+        assert_eq!(res.depth_results[0].checkmates, want);
+    }
 }
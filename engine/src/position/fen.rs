@@ -1,6 +1,8 @@
 use crate::bitboard::Square::*;
 use crate::bitboard::{BitBoard, Square};
-use crate::position::{CastlingRights, Piece, Pieces, Position, Side, Sides, State};
+use crate::position::{
+    CastlingRights, Piece, Pieces, Position, Side, Sides, State, SEVENTY_FIVE_MOVE_RULE_HALF_MOVES,
+};
 use std::str::FromStr;
 
 use strum::IntoEnumIterator;
@@ -22,7 +24,7 @@ pub enum FenParseError {
     #[error("en passant target: got {0}")]
     EnPassantTarget(String),
 
-    #[error("halfmove clock: want 0 <= x < 50 got {0}")]
+    #[error("halfmove clock: want 0 <= x <= 150 got {0}")]
     HalfmoveClock(String),
 
     #[error("full move counter: want 0 <= x < 65_535 got {0}")]
@@ -49,9 +51,9 @@ impl Position {
             .parse::<u8>()
             .map_err(|_| FenParseError::HalfmoveClock(fields[4].to_string()))?;
 
-        // Half move counter must be in 0..=49
+        // Half move counter must be in 0..=150, the game is over after that
         // Don't have to check if less than zero because u8 min value = 0
-        if half_move_clock >= 50 {
+        if half_move_clock > SEVENTY_FIVE_MOVE_RULE_HALF_MOVES {
             Err(FenParseError::HalfmoveClock(fields[4].to_string()))?
         }
 
@@ -67,11 +69,7 @@ impl Position {
             full_move_counter,
         };
 
-        Ok(Position {
-            sides,
-            pieces,
-            state,
-        })
+        Ok(Position::new(state, sides, pieces))
     }
 
     pub fn to_fen(&self) -> String {
@@ -365,4 +363,21 @@ mod tests {
         assert_eq!(got, fen);
         Ok(())
     }
+
+    #[test_case("8/8/4k3/8/8/3K4/8/7R w - - 0 1", 0 ; "zero")]
+    #[test_case("8/8/4k3/8/8/3K4/8/7R w - - 50 1", 50 ; "fifty")]
+    #[test_case("8/8/4k3/8/8/3K4/8/7R w - - 100 1", 100 ; "fifty move rule")]
+    #[test_case("8/8/4k3/8/8/3K4/8/7R w - - 150 1", 150 ; "seventy five move rule")]
+    fn test_half_move_clock_from_fen(fen: &str, want: u8) -> TestResult {
+        let pos = Position::from_fen(fen)?;
+        assert_eq!(pos.state.half_move_clock, want);
+        Ok(())
+    }
+
+    #[test_case("8/8/4k3/8/8/3K4/8/7R w - - 151 1" ; "past seventy five move rule")]
+    #[test_case("8/8/4k3/8/8/3K4/8/7R w - - -1 1" ; "negative")]
+    fn test_half_move_clock_from_fen_invalid(fen: &str) {
+        let got = Position::from_fen(fen);
+        assert!(matches!(got, Err(FenParseError::HalfmoveClock(_))));
+    }
 }
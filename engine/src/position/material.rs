@@ -0,0 +1,97 @@
+use crate::bitboard::BitBoard;
+use crate::position::{Piece, Position, Side};
+
+const LIGHT_SQUARES: BitBoard = BitBoard::from_val(0x55AA_55AA_55AA_55AA);
+const DARK_SQUARES: BitBoard = LIGHT_SQUARES.const_bit_not();
+
+impl Position {
+    /// Whether neither side can checkmate by any sequence of legal moves, e.g. KvK, KBvK, KNvK,
+    /// or only bishops that are all on the same colored squares. The game is then drawn.
+    pub fn is_insufficient_material(&self) -> bool {
+        if !self.major_pieces_and_pawns().is_empty() {
+            return false;
+        }
+
+        let knights = self.both_sides(Piece::Knight);
+        let bishops = self.both_sides(Piece::Bishop);
+        let minor_pieces = knights | bishops;
+
+        // A lone minor piece can't mate, but with two pieces the other side can be helpmated,
+        // except when they're bishops that can't ever cover squares of the other color
+        minor_pieces.num_squares_set() <= 1
+            || (knights.is_empty()
+                && ((bishops & LIGHT_SQUARES).is_empty() || (bishops & DARK_SQUARES).is_empty()))
+    }
+
+    /// Whether `side` has enough material to force checkmate. Unlike
+    /// `Position::is_insufficient_material`, this is about one side: with just a minor piece (or
+    /// bishops on one color), a side can't win, but the game isn't over since the other side
+    /// still can.
+    pub fn has_mating_material(&self, side: Side) -> bool {
+        let side_pieces = |piece| self.pieces.get(piece).get(side);
+
+        if !(side_pieces(Piece::Pawn) | side_pieces(Piece::Rook) | side_pieces(Piece::Queen))
+            .is_empty()
+        {
+            return true;
+        }
+
+        let knights = side_pieces(Piece::Knight);
+        let bishops = side_pieces(Piece::Bishop);
+
+        let has_bishop_pair =
+            !(bishops & LIGHT_SQUARES).is_empty() && !(bishops & DARK_SQUARES).is_empty();
+        let has_bishop_and_knight = !bishops.is_empty() && !knights.is_empty();
+
+        // Two knights can't force mate either, only with help from the other side
+        has_bishop_pair || has_bishop_and_knight
+    }
+
+    fn both_sides(&self, piece: Piece) -> BitBoard {
+        let pieces = self.pieces.get(piece);
+        pieces.get(Side::White) | pieces.get(Side::Black)
+    }
+
+    fn major_pieces_and_pawns(&self) -> BitBoard {
+        self.both_sides(Piece::Pawn) | self.both_sides(Piece::Rook) | self.both_sides(Piece::Queen)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+    use testresult::TestResult;
+
+    #[test_case("8/8/4k3/8/8/3K4/8/8 w - - 0 1", true ; "king vs king")]
+    #[test_case("8/8/4k3/8/8/3K4/3B4/8 w - - 0 1", true ; "king bishop vs king")]
+    #[test_case("8/8/4k3/8/8/3K4/3N4/8 w - - 0 1", true ; "king knight vs king")]
+    #[test_case("8/4b3/4k3/8/8/3K4/3B4/8 w - - 0 1", true ; "same colored bishops")]
+    #[test_case("8/8/4k3/8/8/3K4/3B1B2/8 w - - 0 1", true ; "same colored bishops one side")]
+    #[test_case("8/3b4/4k3/8/8/3K4/3B4/8 w - - 0 1", false ; "opposite colored bishops")]
+    #[test_case("8/3n4/4k3/8/8/3K4/3N4/8 w - - 0 1", false ; "knight vs knight")]
+    #[test_case("8/8/4k3/8/8/3K4/3NN3/8 w - - 0 1", false ; "two knights")]
+    #[test_case("8/8/4k3/8/8/3K4/3P4/8 w - - 0 1", false ; "pawn")]
+    #[test_case("8/8/4k3/8/8/3K4/3R4/8 w - - 0 1", false ; "rook")]
+    #[test_case("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", false ; "start")]
+    fn test_is_insufficient_material(fen: &str, want: bool) -> TestResult {
+        let position = Position::from_fen(fen)?;
+        assert_eq!(position.is_insufficient_material(), want);
+        Ok(())
+    }
+
+    #[test_case("8/8/4k3/8/8/3K4/8/8 w - - 0 1", false, false ; "king vs king")]
+    #[test_case("8/8/4k3/8/8/3K4/3B4/8 w - - 0 1", false, false ; "king bishop vs king")]
+    #[test_case("8/8/4k3/8/8/3K4/3NN3/8 w - - 0 1", false, false ; "two knights")]
+    #[test_case("8/8/4k3/8/8/3K4/3B1B2/8 w - - 0 1", false, false ; "same colored bishops")]
+    #[test_case("8/8/4k3/8/8/3K4/3BB3/8 w - - 0 1", true, false ; "bishop pair")]
+    #[test_case("8/8/4k3/8/8/3K4/3BN3/8 w - - 0 1", true, false ; "bishop and knight")]
+    #[test_case("8/8/4k3/8/8/3K4/3N4/r7 w - - 0 1", false, true ; "knight vs rook")]
+    #[test_case("8/p7/4k3/8/8/3K4/3B4/8 w - - 0 1", false, true ; "bishop vs pawn")]
+    fn test_has_mating_material(fen: &str, want_white: bool, want_black: bool) -> TestResult {
+        let position = Position::from_fen(fen)?;
+        assert_eq!(position.has_mating_material(Side::White), want_white);
+        assert_eq!(position.has_mating_material(Side::Black), want_black);
+        Ok(())
+    }
+}
@@ -1,14 +1,19 @@
 use std::fmt;
+use std::str::FromStr;
 
 use arrayvec::ArrayVec;
 use serde::{Deserialize, Serialize};
 use strum::IntoEnumIterator;
-use strum_macros::{Display, EnumIter};
+use strum_macros::{Display, EnumIter, FromRepr};
 
 use crate::bitboard::Square::*;
 use crate::bitboard::{BitBoard, Direction, Square};
 
+use self::zobrist::ZOBRIST_KEYS;
+
 mod fen;
+mod material;
+mod zobrist;
 
 #[derive(thiserror::Error, Debug)]
 pub enum PositionError {
@@ -24,10 +29,16 @@ pub enum PositionError {
     #[error("to_move is the other side, for move: {0} {1} -> {2}")]
     MoveNotToMove(String, String, String),
 
-    #[error("game is over, half move clock is at 50: move {0}")]
-    GameOverHalfMoveClock(String),
+    #[error("move: want long algebraic notation like e2e4 or e7e8q, got {0}")]
+    FromStrMove(String),
 }
 
+/// Half moves without a capture or pawn move after which the game is drawn by the fifty move rule
+pub(crate) const FIFTY_MOVE_RULE_HALF_MOVES: u8 = 100;
+/// Half moves without a capture or pawn move after which the game is drawn automatically by the
+/// seventy-five move rule, so no position from a real game can have a higher half move clock
+pub(crate) const SEVENTY_FIVE_MOVE_RULE_HALF_MOVES: u8 = 150;
+
 #[derive(Debug, PartialEq, Eq, EnumIter, Clone, Copy, Display, Deserialize, Serialize)]
 pub enum Side {
     White,
@@ -44,7 +55,9 @@ impl Side {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, EnumIter, Clone, Copy, Display, Hash, Deserialize, Serialize)]
+#[derive(
+    Debug, PartialEq, Eq, EnumIter, FromRepr, Clone, Copy, Display, Hash, Deserialize, Serialize,
+)]
 pub enum Piece {
     Pawn,
     Knight,
@@ -138,6 +151,36 @@ impl fmt::Display for Move {
     }
 }
 
+/// Parses long algebraic notation as used by UCI, e.g. `e2e4`, or `e7e8q` for a promotion.
+impl FromStr for Move {
+    type Err = PositionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || PositionError::FromStrMove(s.to_string());
+
+        if !s.is_ascii() || !(4..=5).contains(&s.len()) {
+            return Err(err());
+        }
+        let src = Square::from_str(&s[0..2].to_uppercase()).map_err(|_| err())?;
+        let dest = Square::from_str(&s[2..4].to_uppercase()).map_err(|_| err())?;
+        let promotion = match s[4..].chars().next() {
+            Some(ch) => match Piece::try_from(ch.to_ascii_lowercase()) {
+                Ok(piece @ (Piece::Knight | Piece::Bishop | Piece::Rook | Piece::Queen)) => {
+                    Some(piece)
+                }
+                _ => return Err(err()),
+            },
+            None => None,
+        };
+
+        Ok(Move {
+            src,
+            dest,
+            promotion,
+        })
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub(crate) struct Sides {
     white: BitBoard,
@@ -250,7 +293,7 @@ impl Pieces {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize, Serialize)]
 pub struct CastlingRights {
     pub white_king_side: bool,
     pub white_queen_side: bool,
@@ -304,20 +347,45 @@ impl State {
     }
 }
 
+/// State that can't be recovered from a position after a move has been made, needed to unmake
+/// the move. Returned by `Position::make_move` and passed to `Position::unmake_move`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MoveUndo {
+    captured_piece: Option<Piece>,
+    castling_rights: CastlingRights,
+    en_passant_target: Option<Square>,
+    half_move_clock: u8,
+    zobrist_key: u64,
+}
+
 #[derive(Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct Position {
     pub state: State,
     pub(crate) sides: Sides,
     pub(crate) pieces: Pieces,
+    zobrist_key: u64,
 }
 
 impl Position {
+    fn new(state: State, sides: Sides, pieces: Pieces) -> Self {
+        let mut position = Self {
+            state,
+            sides,
+            pieces,
+            zobrist_key: 0,
+        };
+        position.zobrist_key = ZOBRIST_KEYS.calc_key(&position);
+        position
+    }
+
     pub fn start() -> Self {
-        Self {
-            state: State::start(),
-            sides: Sides::start(),
-            pieces: Pieces::start(),
-        }
+        Self::new(State::start(), Sides::start(), Pieces::start())
+    }
+
+    /// Zobrist hash of this position. Two positions with the same pieces, side to move, castling
+    /// rights and en passant target will have the same key.
+    pub fn zobrist_key(&self) -> u64 {
+        self.zobrist_key
     }
 
     pub fn is_piece_at(&self, square: Square) -> Option<(Piece, Side)> {
@@ -333,11 +401,29 @@ impl Position {
         None
     }
 
-    pub fn make_move(&mut self, mve: &Move) -> Result<(), PositionError> {
-        if self.state.half_move_clock >= 50 {
-            return Err(PositionError::GameOverHalfMoveClock(mve.to_string()));
-        }
+    /// Every occupied square.
+    pub fn occupancy(&self) -> BitBoard {
+        self.sides.get(Side::White) | self.sides.get(Side::Black)
+    }
+
+    /// The squares occupied by `side`'s pieces.
+    pub fn side_occupancy(&self, side: Side) -> BitBoard {
+        self.sides.get(side)
+    }
 
+    /// The squares occupied by `side`'s pieces of type `piece`.
+    pub fn piece_occupancy(&self, piece: Piece, side: Side) -> BitBoard {
+        self.pieces.get(piece).get(side)
+    }
+
+    /// Whether 50 moves by each side have been played without a capture or pawn move. The game
+    /// is then a draw, unless the last of those moves checkmated, so this should be checked after
+    /// checkmate.
+    pub fn is_fifty_move_draw(&self) -> bool {
+        self.state.half_move_clock >= FIFTY_MOVE_RULE_HALF_MOVES
+    }
+
+    pub fn make_move(&mut self, mve: &Move) -> Result<MoveUndo, PositionError> {
         if self.state.to_move == Side::Black {
             self.state.full_move_counter += 1;
         }
@@ -350,6 +436,20 @@ impl Position {
                     mve.dest.to_string(),
                 ))
             } else {
+                let mut undo = MoveUndo {
+                    captured_piece: None,
+                    castling_rights: self.state.castling_rights,
+                    en_passant_target: self.state.en_passant_target,
+                    half_move_clock: self.state.half_move_clock,
+                    zobrist_key: self.zobrist_key,
+                };
+
+                // Remove the keys for the state before this move, the keys for the new state are
+                // added back once it has been updated
+                self.zobrist_key ^= ZOBRIST_KEYS.castling_rights(&self.state.castling_rights);
+                self.zobrist_key ^= ZOBRIST_KEYS.en_passant(self);
+                self.zobrist_key ^= ZOBRIST_KEYS.side_to_move(side);
+
                 self.state.to_move = side.opposite_side();
 
                 if piece == Piece::Pawn || self.is_piece_at(mve.dest).is_some() {
@@ -387,6 +487,8 @@ impl Position {
                         .get_mut(opp_piece)
                         .get_mut(opp_side)
                         .clear_square(dest_sq);
+                    self.zobrist_key ^= ZOBRIST_KEYS.piece(opp_piece, opp_side, dest_sq);
+                    undo.captured_piece = Some(opp_piece);
 
                     if opp_piece == Piece::Rook {
                         if mve.dest == H1 {
@@ -419,6 +521,7 @@ impl Position {
 
                 if piece == Piece::Pawn && (mve.dest >= A8 || mve.dest <= H1) {
                     // Promotion
+                    let promotion = mve.promotion.unwrap();
                     self.sides.get_mut(side).move_piece(mve.src, mve.dest);
 
                     self.pieces
@@ -426,61 +529,74 @@ impl Position {
                         .get_mut(side)
                         .clear_square(mve.src);
                     self.pieces
-                        .get_mut(mve.promotion.unwrap())
+                        .get_mut(promotion)
                         .get_mut(side)
                         .set_square(mve.dest);
 
-                    return Ok(());
-                }
+                    self.zobrist_key ^= ZOBRIST_KEYS.piece(Piece::Pawn, side, mve.src);
+                    self.zobrist_key ^= ZOBRIST_KEYS.piece(promotion, side, mve.dest);
+                } else {
+                    if piece == Piece::King {
+                        if side == Side::White {
+                            self.state.castling_rights.white_king_side = false;
+                            self.state.castling_rights.white_queen_side = false;
+                        } else {
+                            self.state.castling_rights.black_king_side = false;
+                            self.state.castling_rights.black_queen_side = false;
+                        }
 
-                if piece == Piece::King {
-                    if side == Side::White {
-                        self.state.castling_rights.white_king_side = false;
-                        self.state.castling_rights.white_queen_side = false;
-                    } else {
-                        self.state.castling_rights.black_king_side = false;
-                        self.state.castling_rights.black_queen_side = false;
-                    }
+                        if mve.src.abs_diff(mve.dest) == 2 {
+                            // Castled
+                            let rook_move = match mve.dest {
+                                C1 => Move::new(A1, D1),
+                                G1 => Move::new(H1, F1),
+                                C8 => Move::new(A8, D8),
+                                G8 => Move::new(H8, F8),
+                                _ => panic!("want: [C1, G1, C8, G8], got: {}", mve.dest),
+                            };
 
-                    if mve.src.abs_diff(mve.dest) == 2 {
-                        // Castled
-                        let rook_move = match mve.dest {
-                            C1 => Move::new(A1, D1),
-                            G1 => Move::new(H1, F1),
-                            C8 => Move::new(A8, D8),
-                            G8 => Move::new(H8, F8),
-                            _ => panic!("want: [C1, G1, C8, G8], got: {}", mve.dest),
-                        };
-
-                        self.sides
-                            .get_mut(side)
-                            .move_piece(rook_move.src, rook_move.dest);
-                        self.pieces
-                            .get_mut(Piece::Rook)
-                            .get_mut(side)
-                            .move_piece(rook_move.src, rook_move.dest);
+                            self.sides
+                                .get_mut(side)
+                                .move_piece(rook_move.src, rook_move.dest);
+                            self.pieces
+                                .get_mut(Piece::Rook)
+                                .get_mut(side)
+                                .move_piece(rook_move.src, rook_move.dest);
+
+                            self.zobrist_key ^=
+                                ZOBRIST_KEYS.piece(Piece::Rook, side, rook_move.src);
+                            self.zobrist_key ^=
+                                ZOBRIST_KEYS.piece(Piece::Rook, side, rook_move.dest);
+                        }
                     }
-                }
 
-                if piece == Piece::Rook {
-                    if mve.src == A1 {
-                        self.state.castling_rights.white_queen_side = false;
-                    } else if mve.src == H1 {
-                        self.state.castling_rights.white_king_side = false;
-                    }
-                    if mve.src == A8 {
-                        self.state.castling_rights.black_queen_side = false;
-                    }
-                    if mve.src == H8 {
-                        self.state.castling_rights.black_king_side = false;
+                    if piece == Piece::Rook {
+                        if mve.src == A1 {
+                            self.state.castling_rights.white_queen_side = false;
+                        } else if mve.src == H1 {
+                            self.state.castling_rights.white_king_side = false;
+                        }
+                        if mve.src == A8 {
+                            self.state.castling_rights.black_queen_side = false;
+                        }
+                        if mve.src == H8 {
+                            self.state.castling_rights.black_king_side = false;
+                        }
                     }
+
+                    self.sides.get_mut(side).move_piece(mve.src, mve.dest);
+                    self.pieces
+                        .get_mut(piece)
+                        .get_mut(side)
+                        .move_piece(mve.src, mve.dest);
+
+                    self.zobrist_key ^= ZOBRIST_KEYS.piece(piece, side, mve.src);
+                    self.zobrist_key ^= ZOBRIST_KEYS.piece(piece, side, mve.dest);
                 }
 
-                self.sides.get_mut(side).move_piece(mve.src, mve.dest);
-                self.pieces
-                    .get_mut(piece)
-                    .get_mut(side)
-                    .move_piece(mve.src, mve.dest);
+                self.zobrist_key ^= ZOBRIST_KEYS.castling_rights(&self.state.castling_rights);
+                self.zobrist_key ^= ZOBRIST_KEYS.en_passant(self);
+                self.zobrist_key ^= ZOBRIST_KEYS.side_to_move(self.state.to_move);
 
                 debug_assert!(
                     !self.pieces.kings.white.is_empty(),
@@ -493,13 +609,143 @@ impl Position {
                     self
                 );
 
-                Ok(())
+                Ok(undo)
             }
         } else {
             Err(PositionError::MoveNoPiece(mve.src.to_string()))
         }
     }
 
+    /// Reverts `mve`, which must be the last move made on this position, using the undo record
+    /// `make_move` returned for it.
+    pub fn unmake_move(&mut self, mve: &Move, undo: MoveUndo) {
+        let side = self.state.to_move.opposite_side();
+
+        if let Some(promotion) = mve.promotion {
+            self.sides.get_mut(side).move_piece(mve.dest, mve.src);
+            self.pieces
+                .get_mut(promotion)
+                .get_mut(side)
+                .clear_square(mve.dest);
+            self.pieces
+                .get_mut(Piece::Pawn)
+                .get_mut(side)
+                .set_square(mve.src);
+        } else {
+            let (piece, _) = self
+                .is_piece_at(mve.dest)
+                .expect("unmade move should have a piece at its destination");
+
+            self.sides.get_mut(side).move_piece(mve.dest, mve.src);
+            self.pieces
+                .get_mut(piece)
+                .get_mut(side)
+                .move_piece(mve.dest, mve.src);
+
+            if piece == Piece::King && mve.src.abs_diff(mve.dest) == 2 {
+                // Castled
+                let rook_move = match mve.dest {
+                    C1 => Move::new(A1, D1),
+                    G1 => Move::new(H1, F1),
+                    C8 => Move::new(A8, D8),
+                    G8 => Move::new(H8, F8),
+                    _ => panic!("want: [C1, G1, C8, G8], got: {}", mve.dest),
+                };
+
+                self.sides
+                    .get_mut(side)
+                    .move_piece(rook_move.dest, rook_move.src);
+                self.pieces
+                    .get_mut(Piece::Rook)
+                    .get_mut(side)
+                    .move_piece(rook_move.dest, rook_move.src);
+            }
+        }
+
+        if let Some(captured_piece) = undo.captured_piece {
+            let opp_side = side.opposite_side();
+
+            let is_en_passant = undo.en_passant_target == Some(mve.dest)
+                && self
+                    .pieces
+                    .get(Piece::Pawn)
+                    .get(side)
+                    .is_square_set(mve.src);
+            let captured_sq = if is_en_passant {
+                let ep_capture_dir = if side == Side::White {
+                    Direction::DecRank
+                } else {
+                    Direction::IncRank
+                };
+
+                let mut ep_capture_bb = BitBoard::from_square(mve.dest);
+                ep_capture_bb.shift(ep_capture_dir);
+                ep_capture_bb.get_lsb()
+            } else {
+                mve.dest
+            };
+
+            self.sides.get_mut(opp_side).set_square(captured_sq);
+            self.pieces
+                .get_mut(captured_piece)
+                .get_mut(opp_side)
+                .set_square(captured_sq);
+        }
+
+        if side == Side::Black {
+            self.state.full_move_counter -= 1;
+        }
+        self.state.to_move = side;
+        self.state.castling_rights = undo.castling_rights;
+        self.state.en_passant_target = undo.en_passant_target;
+        self.state.half_move_clock = undo.half_move_clock;
+        self.zobrist_key = undo.zobrist_key;
+    }
+
+    /// Passes the turn to the other side without moving a piece, for null move pruning. Not a
+    /// legal move, so shouldn't be made when in check. The half move clock is reset, so positions
+    /// from before it aren't counted as repetitions.
+    pub fn make_null_move(&mut self) -> MoveUndo {
+        let undo = MoveUndo {
+            captured_piece: None,
+            castling_rights: self.state.castling_rights,
+            en_passant_target: self.state.en_passant_target,
+            half_move_clock: self.state.half_move_clock,
+            zobrist_key: self.zobrist_key,
+        };
+
+        if self.state.to_move == Side::Black {
+            self.state.full_move_counter += 1;
+        }
+        self.zobrist_key ^= ZOBRIST_KEYS.en_passant(self);
+        self.zobrist_key ^= ZOBRIST_KEYS.side_to_move(self.state.to_move);
+        self.state.to_move = self.state.to_move.opposite_side();
+        self.state.en_passant_target = None;
+        self.state.half_move_clock = 0;
+        self.zobrist_key ^= ZOBRIST_KEYS.side_to_move(self.state.to_move);
+
+        undo
+    }
+
+    /// Reverts a null move, using the undo record `make_null_move` returned for it.
+    pub fn unmake_null_move(&mut self, undo: MoveUndo) {
+        self.state.to_move = self.state.to_move.opposite_side();
+        if self.state.to_move == Side::Black {
+            self.state.full_move_counter -= 1;
+        }
+        self.state.en_passant_target = undo.en_passant_target;
+        self.state.half_move_clock = undo.half_move_clock;
+        self.zobrist_key = undo.zobrist_key;
+    }
+
+    /// Whether `side` has any pieces besides its king and pawns. Without them zugzwang is common,
+    /// so passing can't be assumed to be the worst move.
+    pub fn has_non_pawn_material(&self, side: Side) -> bool {
+        [Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen]
+            .into_iter()
+            .any(|piece| !self.piece_occupancy(piece, side).is_empty())
+    }
+
     pub fn remove_piece(&mut self, square: Square) -> Result<(), PositionError> {
         if let Some((piece, side)) = self.is_piece_at(square) {
             self.sides.get_mut(side).clear_square(square);
@@ -507,6 +753,7 @@ impl Position {
                 .get_mut(piece)
                 .get_mut(side)
                 .clear_square(square);
+            self.zobrist_key ^= ZOBRIST_KEYS.piece(piece, side, square);
             Ok(())
         } else {
             Err(PositionError::RemoveNoPiece(square.to_string()))
@@ -557,6 +804,7 @@ impl fmt::Debug for Position {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::move_gen::{GenerateMoves, HYPERBOLA_QUINTESSENCE_MOVE_GEN};
     use test_case::test_case;
     use testresult::TestResult;
 
@@ -582,6 +830,23 @@ mod tests {
         assert_eq!(pos.state.to_move, Side::White);
     }
 
+    #[test_case("e2e4", Move::new(E2, E4) ; "move")]
+    #[test_case("e7e8q", Move::with_promotion(E7, E8, Piece::Queen) ; "promotion")]
+    #[test_case("a2a1N", Move::with_promotion(A2, A1, Piece::Knight) ; "uppercase promotion")]
+    fn test_move_from_str(input: &str, want: Move) -> TestResult {
+        assert_eq!(Move::from_str(input)?, want);
+        Ok(())
+    }
+
+    #[test_case("e2" ; "too short")]
+    #[test_case("e7e8qq" ; "too long")]
+    #[test_case("e7e8k" ; "promotion to king")]
+    #[test_case("e9e8" ; "not a square")]
+    #[test_case("0000" ; "null move")]
+    fn test_move_from_str_invalid(input: &str) {
+        assert!(Move::from_str(input).is_err());
+    }
+
     #[test_case(Position::start(), Move::new(D2, D4))]
     fn test_make_move(mut position: Position, mve: Move) {
         assert!(position.is_piece_at(mve.src).is_some());
@@ -601,7 +866,24 @@ mod tests {
         assert!(res.is_err());
     }
 
-    #[test_case(Position::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap(), 
+    #[test_case("8/8/4k3/8/8/3K4/8/7R w - - 98 60", Move::new(H1, H2), 99, false ; "before fifty move rule")]
+    #[test_case("8/8/4k3/8/8/3K4/8/7R w - - 99 60", Move::new(H1, H2), 100, true ; "fifty move rule")]
+    #[test_case("8/8/4k3/8/8/3K4/8/7R w - - 120 70", Move::new(H1, H2), 121, true ; "past fifty move rule")]
+    #[test_case("8/8/4k3/8/8/3K4/7P/7R w - - 99 60", Move::new(H2, H3), 0, false ; "reset by pawn move")]
+    fn test_make_move_half_move_clock(
+        fen: &str,
+        mve: Move,
+        want_half_move_clock: u8,
+        want_fifty_move_draw: bool,
+    ) -> TestResult {
+        let mut position = Position::from_fen(fen)?;
+        position.make_move(&mve)?;
+        assert_eq!(position.state.half_move_clock, want_half_move_clock);
+        assert_eq!(position.is_fifty_move_draw(), want_fifty_move_draw);
+        Ok(())
+    }
+
+    #[test_case(Position::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap(),
         Move::new(A2, A4),A3 ; "kiwipete")]
     fn test_make_move_ep_target(
         mut position: Position,
@@ -617,6 +899,95 @@ mod tests {
         Ok(())
     }
 
+    #[test_case(Position::start(), &[Move::new(E2, E4), Move::new(D7, D5), Move::new(E4, D5), Move::new(G8, F6)] ; "capture")]
+    #[test_case(Position::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap(),
+        &[Move::new(E1, G1), Move::new(E8, C8), Move::new(A2, A4), Move::new(B4, A3)] ; "castling and en passant")]
+    #[test_case(Position::from_fen("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1").unwrap(),
+        &[Move::new(G1, H1), Move::with_promotion(B2, A1, Piece::Queen), Move::with_promotion(A7, B8, Piece::Knight), Move::new(H8, H7)] ; "promotions")]
+    fn test_zobrist_key_incremental(mut position: Position, moves: &[Move]) -> TestResult {
+        for mve in moves {
+            position.make_move(mve)?;
+            assert_eq!(position.zobrist_key(), ZOBRIST_KEYS.calc_key(&position));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_zobrist_key_transposition() -> TestResult {
+        let start = Position::start();
+
+        let mut knights_back = start.clone();
+        for mve in [
+            Move::new(G1, F3),
+            Move::new(G8, F6),
+            Move::new(F3, G1),
+            Move::new(F6, G8),
+        ] {
+            knights_back.make_move(&mve)?;
+        }
+        assert_eq!(knights_back.zobrist_key(), start.zobrist_key());
+
+        // Same position, except only one of these has an en passant target, which no pawn can
+        // capture onto
+        let mut double_push = start.clone();
+        double_push.make_move(&Move::new(E2, E4))?;
+        let no_ep_target =
+            Position::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1")?;
+        assert_eq!(double_push.zobrist_key(), no_ep_target.zobrist_key());
+
+        // Same again, except a pawn can capture en passant
+        let mut double_push =
+            Position::from_fen("rnbqkbnr/ppp1pppp/8/8/3p4/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")?;
+        double_push.make_move(&Move::new(E2, E4))?;
+        let no_ep_target =
+            Position::from_fen("rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1")?;
+        assert_ne!(double_push.zobrist_key(), no_ep_target.zobrist_key());
+
+        Ok(())
+    }
+
+    #[test_case(Position::start() ; "start")]
+    #[test_case(Position::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap() ; "kiwipete")]
+    #[test_case(Position::from_fen("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1").unwrap() ; "promotions")]
+    #[test_case(Position::from_fen("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3").unwrap() ; "en passant")]
+    fn test_unmake_move(mut position: Position) -> TestResult {
+        let move_gen = HYPERBOLA_QUINTESSENCE_MOVE_GEN;
+
+        // Two plies deep, so moves made from positions reached by captures and castling are
+        // covered as well
+        for mve in move_gen.gen_moves(&position) {
+            let before = position.clone();
+            let undo = position.make_move(&mve)?;
+
+            for reply in move_gen.gen_moves(&position) {
+                let before_reply = position.clone();
+                let reply_undo = position.make_move(&reply)?;
+                position.unmake_move(&reply, reply_undo);
+                assert_eq!(position, before_reply, "{} {}", mve, reply);
+            }
+
+            position.unmake_move(&mve, undo);
+            assert_eq!(position, before, "{}", mve);
+        }
+        Ok(())
+    }
+
+    #[test_case(Position::start(), Position::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1").unwrap() ; "start")]
+    #[test_case(Position::from_fen("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3").unwrap(),
+        Position::from_fen("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 3").unwrap() ; "clears en passant target")]
+    #[test_case(Position::from_fen("4k3/8/8/8/8/8/8/4K2R b K - 7 40").unwrap(),
+        Position::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 41").unwrap() ; "black to move")]
+    fn test_make_null_move(mut position: Position, want: Position) {
+        let before = position.clone();
+
+        let undo = position.make_null_move();
+        assert_eq!(position, want);
+        assert_eq!(position.zobrist_key(), ZOBRIST_KEYS.calc_key(&position));
+
+        position.unmake_null_move(undo);
+        assert_eq!(position, before);
+    }
+
     #[test_case(Move::new(A1, G7), "A1 -> G7" ; "no promotion")]
     #[test_case(Move::with_promotion(F7, B6, Piece::Queen), "F7 -> B6 (Queen)" ; "with promotion")]
     fn test_move_debug(mve: Move, want: &str) {
@@ -0,0 +1,189 @@
+use strum::IntoEnumIterator;
+
+use crate::bitboard::{BitBoard, Direction, Square};
+use crate::position::{CastlingRights, Piece, Position, Side};
+
+// Source: https://www.chessprogramming.org/Zobrist_Hashing
+pub(crate) struct ZobristKeys {
+    pieces: [[[u64; 64]; 6]; 2],
+    castling_rights: [u64; 16],
+    en_passant_files: [u64; 8],
+    black_to_move: u64,
+}
+
+// Seed is arbitrary, it just needs to stay the same so keys are reproducible between runs
+const ZOBRIST_SEED: u64 = 0x2C1B_3C6D_4F4A_8E95;
+
+// Source: https://prng.di.unimi.it/splitmix64.c
+const fn split_mix_64(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    (state, z ^ (z >> 31))
+}
+
+const fn calc_zobrist_keys() -> ZobristKeys {
+    let mut state = ZOBRIST_SEED;
+    let mut rand;
+
+    let mut pieces = [[[0; 64]; 6]; 2];
+    let mut side_idx = 0;
+    while side_idx < 2 {
+        let mut piece_idx = 0;
+        while piece_idx < 6 {
+            let mut sq_idx = 0;
+            while sq_idx < 64 {
+                (state, rand) = split_mix_64(state);
+                pieces[side_idx][piece_idx][sq_idx] = rand;
+                sq_idx += 1;
+            }
+            piece_idx += 1;
+        }
+        side_idx += 1;
+    }
+
+    // Each castling right gets its own key, and every combination of rights is the xor of the
+    // keys of the rights it contains. This way, losing a right can be undone by xoring its key.
+    let mut castling_right_keys = [0; 4];
+    let mut right_idx = 0;
+    while right_idx < 4 {
+        (state, rand) = split_mix_64(state);
+        castling_right_keys[right_idx] = rand;
+        right_idx += 1;
+    }
+
+    let mut castling_rights = [0; 16];
+    let mut rights_idx = 0;
+    while rights_idx < 16 {
+        let mut key = 0;
+        let mut right_idx = 0;
+        while right_idx < 4 {
+            if rights_idx & (1 << right_idx) != 0 {
+                key ^= castling_right_keys[right_idx];
+            }
+            right_idx += 1;
+        }
+        castling_rights[rights_idx] = key;
+        rights_idx += 1;
+    }
+
+    let mut en_passant_files = [0; 8];
+    let mut file_idx = 0;
+    while file_idx < 8 {
+        (state, rand) = split_mix_64(state);
+        en_passant_files[file_idx] = rand;
+        file_idx += 1;
+    }
+
+    (_, rand) = split_mix_64(state);
+    let black_to_move = rand;
+
+    ZobristKeys {
+        pieces,
+        castling_rights,
+        en_passant_files,
+        black_to_move,
+    }
+}
+
+impl ZobristKeys {
+    pub(crate) fn piece(&self, piece: Piece, side: Side, square: Square) -> u64 {
+        self.pieces[side as usize][piece as usize][square as usize]
+    }
+
+    pub(crate) fn castling_rights(&self, castling_rights: &CastlingRights) -> u64 {
+        let idx = usize::from(castling_rights.white_king_side)
+            | usize::from(castling_rights.white_queen_side) << 1
+            | usize::from(castling_rights.black_king_side) << 2
+            | usize::from(castling_rights.black_queen_side) << 3;
+        self.castling_rights[idx]
+    }
+
+    /// The en passant target is only hashed when a pawn of the side to move can capture onto it.
+    /// Otherwise the position is the same as without one, and has to hash the same for
+    /// repetitions to be found.
+    pub(crate) fn en_passant(&self, position: &Position) -> u64 {
+        let Some(ep_target) = position.state.en_passant_target else {
+            return 0;
+        };
+        let side = position.state.to_move;
+        let behind = match side {
+            Side::White => Direction::DecRank,
+            Side::Black => Direction::IncRank,
+        };
+        let capturers = BitBoard::from_square_shifts(
+            ep_target,
+            &[&[behind, Direction::DecFile], &[behind, Direction::IncFile]],
+        );
+        if (capturers & position.pieces.get(Piece::Pawn).get(side)).is_empty() {
+            return 0;
+        }
+        let (_, file) = ep_target.to_rank_file();
+        self.en_passant_files[file as usize]
+    }
+
+    pub(crate) fn side_to_move(&self, side: Side) -> u64 {
+        match side {
+            Side::White => 0,
+            Side::Black => self.black_to_move,
+        }
+    }
+
+    /// Calculates the key of a position from scratch. During a game the key is instead updated
+    /// incrementally, see `Position::make_move`.
+    pub(crate) fn calc_key(&self, position: &Position) -> u64 {
+        let mut key = 0;
+
+        for side in Side::iter() {
+            for piece in Piece::iter() {
+                let mut pieces = position.pieces.get(piece).get(side);
+                while !pieces.is_empty() {
+                    key ^= self.piece(piece, side, pieces.pop_lsb());
+                }
+            }
+        }
+
+        key ^= self.castling_rights(&position.state.castling_rights);
+        key ^= self.en_passant(position);
+        key ^= self.side_to_move(position.state.to_move);
+
+        key
+    }
+}
+
+pub(crate) static ZOBRIST_KEYS: ZobristKeys = calc_zobrist_keys();
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use test_case::test_case;
+
+    #[test]
+    fn test_keys_unique() {
+        let mut keys = HashSet::new();
+        for side in ZOBRIST_KEYS.pieces {
+            for piece in side {
+                for key in piece {
+                    assert!(keys.insert(key));
+                }
+            }
+        }
+        for key in &ZOBRIST_KEYS.castling_rights[1..] {
+            assert!(keys.insert(*key));
+        }
+        for key in ZOBRIST_KEYS.en_passant_files {
+            assert!(keys.insert(key));
+        }
+        assert!(keys.insert(ZOBRIST_KEYS.black_to_move));
+    }
+
+    #[test_case(CastlingRights::new(false, false, false, false), 0 ; "none")]
+    #[test_case(CastlingRights::new(true, true, true, true), ZOBRIST_KEYS.castling_rights[1] ^ ZOBRIST_KEYS.castling_rights[2] ^ ZOBRIST_KEYS.castling_rights[4] ^ ZOBRIST_KEYS.castling_rights[8] ; "all")]
+    fn test_castling_rights(castling_rights: CastlingRights, want: u64) {
+        let got = ZOBRIST_KEYS.castling_rights(&castling_rights);
+        assert_eq!(got, want);
+    }
+}
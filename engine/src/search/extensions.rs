@@ -0,0 +1,57 @@
+use crate::evaluation::Eval;
+use crate::position::{Move, Piece, Position, Side};
+
+// Source: https://www.chessprogramming.org/Extensions
+//
+// Forcing moves are searched a ply deeper, so tactics aren't cut off by the horizon right where
+// they start to matter. Each move is extended by at most a ply, and each line only has a limited
+// budget of extensions, so that long forcing sequences like perpetual checks can't make the search
+// explode.
+
+/// Singular extensions are only tried with at least this many plies left, since they cost a
+/// search of every other move.
+pub(crate) const SINGULAR_EXTENSION_MIN_DEPTH: u8 = 6;
+/// The transposition table entry for a singular extension can be at most this many plies
+/// shallower than the current search.
+pub(crate) const SINGULAR_EXTENSION_MAX_TT_DEPTH_DIFF: u8 = 3;
+
+/// Centipawns per ply left that every other move has to be worse than the transposition table
+/// move by, for it to be singular.
+const SINGULAR_MARGIN_PER_PLY: i32 = 5;
+
+/// Eval that every move besides the transposition table move has to fail low against, with
+/// `depth_left` plies left, for that move to be singular and extended.
+pub(crate) fn singular_beta(tt_score: i32, depth_left: u8) -> Eval {
+    Eval::cp(tt_score - SINGULAR_MARGIN_PER_PLY * i32::from(depth_left))
+}
+
+/// Whether `mve`, which `side` just made in `position`, pushed a pawn to its 7th rank, one move
+/// from promoting.
+pub(crate) fn is_pawn_push_to_seventh(position: &Position, side: Side, mve: &Move) -> bool {
+    let dest = match side {
+        Side::White => mve.dest,
+        Side::Black => mve.dest.flip(),
+    };
+    position.is_piece_at(mve.dest) == Some((Piece::Pawn, side)) && dest.to_rank_file().0 == 6
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+    use testresult::TestResult;
+
+    use super::*;
+    use crate::bitboard::Square::*;
+
+    #[test_case("4k3/8/2P5/8/8/8/8/4K3 w - - 0 1", Move::new(C6, C7), true ; "white pawn")]
+    #[test_case("4k3/8/8/8/8/2p5/8/4K3 b - - 0 1", Move::new(C3, C2), true ; "black pawn")]
+    #[test_case("4k3/8/8/2P5/8/8/8/4K3 w - - 0 1", Move::new(C5, C6), false ; "white pawn to 6th")]
+    #[test_case("4k3/8/2R5/8/8/8/8/4K3 w - - 0 1", Move::new(C6, C7), false ; "rook")]
+    fn test_is_pawn_push_to_seventh(fen: &str, mve: Move, want: bool) -> TestResult {
+        let mut position = Position::from_fen(fen)?;
+        let side = position.state.to_move;
+        position.make_move(&mve)?;
+        assert_eq!(is_pawn_push_to_seventh(&position, side, &mve), want);
+        Ok(())
+    }
+}
@@ -0,0 +1,1354 @@
+use core::panic;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, debug_span, enabled, error, info, warn};
+
+use crate::evaluation::{Eval, EvaluatePosition};
+use crate::game::{count_repetitions, status_from_moves, Game, GameStatus};
+use crate::move_gen::{GenType, GenerateMoves, MoveList};
+use crate::position::{Move, Piece, Position};
+use crate::Side;
+use crate::TRACING_TARGET_SEARCH;
+
+use self::extensions::{
+    is_pawn_push_to_seventh, singular_beta, SINGULAR_EXTENSION_MAX_TT_DEPTH_DIFF,
+    SINGULAR_EXTENSION_MIN_DEPTH,
+};
+use self::move_ordering::{is_quiet, HistoryTable, KillerMoves, MovePicker};
+use self::pruning::{
+    futility_margin, late_move_pruning_count, late_move_reduction, LATE_MOVE_REDUCTION_MIN_DEPTH,
+    LATE_MOVE_REDUCTION_MIN_MOVES, MAX_PRUNING_DEPTH,
+};
+use self::pv_table::PvTable;
+use self::transposition_table::{Bound, TranspositionTableEntry};
+pub use self::transposition_table::{TranspositionTable, DEFAULT_TRANSPOSITION_TABLE_SIZE_MB};
+
+mod extensions;
+mod move_ordering;
+mod pruning;
+mod pv_table;
+mod transposition_table;
+
+/// Deepest ply searched, so that quiescence search can't go on forever with checks.
+const MAX_SEARCH_DEPTH: u64 = 128;
+
+/// Centipawns on either side of the last iteration's eval that the root is first searched with.
+/// Doubled on the side the eval falls outside of, up to `MAX_ASPIRATION_DELTA`.
+const ASPIRATION_DELTA: i32 = 25;
+const MAX_ASPIRATION_DELTA: i32 = 800;
+
+/// Window that every `Eval` is inside of.
+const FULL_WINDOW: (Eval, Eval) = (Eval::INFINITY.flip(), Eval::INFINITY);
+
+/// Each thread adds the nodes it searches to the shared count in batches of this many, so that
+/// threads don't all write to it on every node.
+const NODES_PER_BATCH: u64 = 256;
+
+/// Null move pruning is only tried with at least this many plies left, since the null move
+/// search is reduced by at least 2 plies on top of the null move itself.
+const NULL_MOVE_MIN_DEPTH: u8 = 3;
+/// Above this many plies left, the null move search is reduced by 3 plies rather than 2.
+const NULL_MOVE_DEEP_REDUCTION_DEPTH: u8 = 6;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SearchParams {
+    pub search_moves: Option<Vec<Move>>,
+    pub ponder: bool,
+    pub white_time: Option<Duration>,
+    pub black_time: Option<Duration>,
+    pub white_inc: Option<Duration>,
+    pub black_inc: Option<Duration>,
+    pub moves_to_go: Option<u16>,
+    pub max_depth: Option<u64>,
+    pub max_nodes: Option<u64>,
+    /// Search for a mate in at most this many moves, without pruning, stopping once one is found.
+    pub mate: Option<u64>,
+    pub move_time: Option<Duration>,
+    pub infinite: bool,
+    /// Number of best lines to find and report, rather than just the best one.
+    pub multi_pv: Option<usize>,
+    /// Number of threads to search with. Only the main one reports and picks the best move, the
+    /// others help by filling the shared transposition table.
+    pub threads: Option<usize>,
+    /// Verify null move cutoffs with a reduced search of the position itself, so zugzwang
+    /// positions aren't pruned.
+    pub null_move_verification: bool,
+}
+
+impl Display for SearchParams {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Print out only non default fields
+        let default = SearchParams::default();
+        let mut parts = vec![];
+
+        if self.search_moves != default.search_moves {
+            parts.push(format!(
+                "search_moves: {:?}",
+                self.search_moves.as_ref().unwrap()
+            ));
+        }
+        if self.ponder != default.ponder {
+            parts.push(format!("ponder: {:?}", self.ponder));
+        }
+        if self.white_time != default.white_time {
+            parts.push(format!(
+                "white_time: {:?}",
+                self.white_time.as_ref().unwrap()
+            ));
+        }
+        if self.black_time != default.black_time {
+            parts.push(format!(
+                "black_time: {:?}",
+                self.black_time.as_ref().unwrap()
+            ));
+        }
+        if self.white_inc != default.white_inc {
+            parts.push(format!("white_inc: {:?}", self.white_inc.as_ref().unwrap()));
+        }
+        if self.black_inc != default.black_inc {
+            parts.push(format!("black_inc: {:?}", self.black_inc.as_ref().unwrap()));
+        }
+        if self.moves_to_go != default.moves_to_go {
+            parts.push(format!(
+                "moves_to_go: {:?}",
+                self.moves_to_go.as_ref().unwrap()
+            ));
+        }
+        if self.max_depth != default.max_depth {
+            parts.push(format!("max_depth: {:?}", self.max_depth.as_ref().unwrap()));
+        }
+        if self.max_nodes != default.max_nodes {
+            parts.push(format!("max_nodes: {:?}", self.max_nodes.as_ref().unwrap()));
+        }
+        if self.mate != default.mate {
+            parts.push(format!("mate: {:?}", self.mate.as_ref().unwrap()));
+        }
+        if self.move_time != default.move_time {
+            parts.push(format!("move_time: {:?}", self.move_time.as_ref().unwrap()));
+        }
+        if self.infinite != default.infinite {
+            parts.push(format!("infinite: {:?}", self.infinite));
+        }
+        if self.multi_pv != default.multi_pv {
+            parts.push(format!("multi_pv: {:?}", self.multi_pv.as_ref().unwrap()));
+        }
+        if self.threads != default.threads {
+            parts.push(format!("threads: {:?}", self.threads.as_ref().unwrap()));
+        }
+        if self.null_move_verification != default.null_move_verification {
+            parts.push(format!(
+                "null_move_verification: {:?}",
+                self.null_move_verification
+            ));
+        }
+        write!(f, "SearchParams: {}", parts.join(", "))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SearchResultInfo {
+    /// Nodes searched by all threads.
+    pub positions_processed: u64,
+    /// Deepest ply reached in the last iteration, including quiescence search.
+    pub seldepth: u64,
+    /// Principal variation from the last completed iteration, starting with the best move.
+    pub pv: Vec<Move>,
+    /// Eval of the best move from the last completed iteration.
+    pub eval: Eval,
+    /// The best `SearchParams::multi_pv` lines from the last completed iteration, best first, so
+    /// the first one is `eval` and `pv`.
+    pub lines: Vec<PvLine>,
+    pub time_elapsed: Duration,
+}
+
+/// One of the lines found by a MultiPV search.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PvLine {
+    pub eval: Eval,
+    pub pv: Vec<Move>,
+}
+
+enum SearchInfo {
+    Depth {
+        plies: u32,
+    },
+    SelDepth {
+        plies: u32,
+    },
+    Time {
+        msec: u64,
+    },
+    Nodes {
+        nodes: u64,
+    },
+    Pv {
+        moves: Vec<Move>,
+    },
+    MultiPv {
+        num: u8,
+    },
+    Score {
+        centipawns: f32,
+        mate_moves: Option<i32>,
+        lower_bound: Option<bool>,
+        upper_bound: Option<bool>,
+    },
+    CurrMove {
+        mve: Move,
+    },
+    CurrMoveNumber {
+        move_num: u32,
+    },
+    HashFull {
+        per_mill_full: u16,
+    },
+    NodesPerSecond {
+        nodes_per_sec: f32,
+    },
+    TableHits {
+        hits: u64,
+    },
+    ShredderHits {
+        hits: u64,
+    },
+    CPULoad {
+        cpu_usage_per_mill: u16,
+    },
+    String {
+        str: String,
+    },
+    Refutation {
+        moves: Vec<Move>,
+    },
+    CurrLine {
+        moves: Vec<Move>,
+        cpu_num: Option<u8>,
+    },
+}
+
+fn moves_to_string(moves: &[Move]) -> String {
+    moves
+        .iter()
+        .map(|mve| mve.to_string())
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+impl Display for SearchInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let info_str = match self {
+            SearchInfo::Depth { plies } => format!("depth {}", plies),
+            SearchInfo::SelDepth { plies } => format!("seldepth {}", plies),
+            SearchInfo::Time { msec } => format!("time {}", msec),
+            SearchInfo::Nodes { nodes } => format!("nodes {}", nodes),
+            SearchInfo::Pv { moves } => format!("pv {}", moves_to_string(moves)),
+            SearchInfo::MultiPv { num } => format!("multipv {}", num),
+            SearchInfo::Score {
+                centipawns,
+                mate_moves,
+                lower_bound,
+                upper_bound,
+            } => {
+                let mut score_str = format!("score cp {}", centipawns);
+
+                if let Some(mate_moves) = mate_moves {
+                    score_str.push_str(format!(" mate {}", mate_moves).as_str());
+                };
+
+                if lower_bound.is_some() {
+                    score_str.push_str(" lowerbound");
+                }
+                if upper_bound.is_some() {
+                    score_str.push_str(" upperbound");
+                }
+
+                score_str
+            }
+            SearchInfo::CurrMove { mve } => format!("currmove {}", mve),
+            SearchInfo::CurrMoveNumber { move_num } => format!("currmovenumber {}", move_num),
+            SearchInfo::HashFull { per_mill_full } => format!("hashfull {}", per_mill_full),
+            SearchInfo::NodesPerSecond { nodes_per_sec } => format!("nps {}", nodes_per_sec),
+            SearchInfo::TableHits { hits } => format!("tbhits {}", hits),
+            SearchInfo::ShredderHits { hits } => format!("sbhits {}", hits),
+            SearchInfo::CPULoad { cpu_usage_per_mill } => format!("cpuload {}", cpu_usage_per_mill),
+            SearchInfo::String { str } => format!("string {}", str),
+            SearchInfo::Refutation { moves } => format!("refutation {}", moves_to_string(moves)),
+            SearchInfo::CurrLine { moves, cpu_num } => {
+                if let Some(cpu_num) = cpu_num {
+                    format!("currline {} {}", cpu_num, moves_to_string(moves))
+                } else {
+                    format!("currline {}", moves_to_string(moves))
+                }
+            }
+        };
+        write!(f, "{}", info_str)
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum SearchError {
+    #[error("Parameters depth and mate are mutually exclusive, both passed: {0}, {1}")]
+    DepthAndMatePassed(u64, u64),
+
+    #[error("Couldn't open search file logs: {0}")]
+    OpenSearchLogsFile(PathBuf),
+}
+
+fn calc_time_per_move(time_left: Duration, time_inc: Duration, moves_to_go: u16) -> Duration {
+    time_left / moves_to_go.into()
+}
+
+pub fn search(
+    game: &Game,
+    params: &SearchParams,
+    move_gen: impl GenerateMoves + std::marker::Copy + Send,
+    position_eval: impl EvaluatePosition + std::marker::Copy + Send,
+    transposition_table: &TranspositionTable,
+    terminate: Arc<AtomicBool>,
+) -> Result<(Option<Move>, SearchResultInfo), SearchError> {
+    let position = game.position();
+    debug_span!("search", position = position.to_fen(), params = ?params);
+    let start = Instant::now();
+
+    let max_depth: usize = match (params.max_depth, params.mate) {
+        (Some(max_depth), None) => max_depth.try_into().unwrap(),
+        // Mating in `mate` moves takes the side to move's `mate` moves and the replies between
+        (None, Some(mate)) => (2 * mate.max(1) - 1).try_into().unwrap(),
+        (Some(max_depth), Some(mate)) => {
+            return Err(SearchError::DepthAndMatePassed(max_depth, mate))
+        }
+        (None, None) => 20,
+    };
+
+    let time_to_use = if position.state.to_move == Side::White {
+        calc_time_per_move(
+            params.white_time.unwrap_or(Duration::from_secs(60)),
+            params.white_inc.unwrap_or(Duration::from_secs(0)),
+            params.moves_to_go.unwrap_or(1),
+        )
+    } else {
+        calc_time_per_move(
+            params.black_time.unwrap_or(Duration::from_secs(60)),
+            params.black_inc.unwrap_or(Duration::from_secs(0)),
+            params.moves_to_go.unwrap_or(1),
+        )
+    };
+    debug!("Time for this move: {:?}", time_to_use);
+
+    // Entries from other searches may have been pruned, so they can't prove there's no mate. The
+    // mate search gets its own table, rather than clearing the one the caller keeps.
+    let mate_transposition_table;
+    let transposition_table = if params.mate.is_some() {
+        mate_transposition_table = transposition_table.new_empty_like();
+        &mate_transposition_table
+    } else {
+        transposition_table
+    };
+
+    let mut moves = move_gen.gen_moves(position);
+
+    // Filter out moves not in search moves
+    if let Some(search_moves) = &params.search_moves {
+        moves.retain(|mve| search_moves.contains(mve));
+    }
+
+    // Source: https://www.chessprogramming.org/Lazy_SMP
+    //
+    // Helper threads search the same root as the main thread, without reporting anything. They
+    // share what they find through the transposition table, which the main thread then gets
+    // cutoffs and better move ordering from. Every other helper searches a ply deeper, so that
+    // the threads don't all search the same tree in lockstep.
+    let num_threads = params.threads.unwrap_or(1).max(1);
+    // Nodes searched by every thread, which a node limit applies to
+    let nodes = AtomicU64::new(0);
+    // Helpers stop once the main thread does, which also stops on `terminate`
+    let stop_helpers = Arc::new(AtomicBool::new(false));
+    let (best_move, mut search_info) = thread::scope(|s| {
+        for thread_num in 1..num_threads {
+            let moves = moves.clone();
+            let nodes = &nodes;
+            let stop_helpers = Arc::clone(&stop_helpers);
+            s.spawn(move || {
+                iterative_deepening(
+                    game,
+                    moves,
+                    params,
+                    max_depth,
+                    time_to_use,
+                    thread_num,
+                    &start,
+                    move_gen,
+                    position_eval,
+                    transposition_table,
+                    nodes,
+                    stop_helpers,
+                )
+            });
+        }
+        let result = iterative_deepening(
+            game,
+            moves,
+            params,
+            max_depth,
+            time_to_use,
+            0,
+            &start,
+            move_gen,
+            position_eval,
+            transposition_table,
+            &nodes,
+            terminate,
+        );
+        stop_helpers.store(true, Ordering::Relaxed);
+        result
+    });
+    // All helpers have finished once the scope ends
+    search_info.positions_processed = nodes.load(Ordering::Relaxed);
+    search_info.time_elapsed = start.elapsed();
+
+    Ok((best_move, search_info))
+}
+
+/// Searches the root at increasing depths until it runs out of depth or time, or is stopped.
+/// Thread 0 is the main thread, which reports its progress and decides when to stop.
+#[allow(clippy::too_many_arguments)]
+fn iterative_deepening(
+    game: &Game,
+    mut moves: MoveList,
+    params: &SearchParams,
+    max_depth: usize,
+    time_to_use: Duration,
+    thread_num: usize,
+    start: &Instant,
+    move_gen: impl GenerateMoves + std::marker::Copy,
+    position_eval: impl EvaluatePosition + std::marker::Copy,
+    transposition_table: &TranspositionTable,
+    nodes: &AtomicU64,
+    terminate: Arc<AtomicBool>,
+) -> (Option<Move>, SearchResultInfo) {
+    // Moves are made and unmade on this copy as the tree is searched
+    let mut position = game.position().clone();
+    // Keys of the positions leading up to the one being searched, for detecting repetitions
+    let mut history = game.history().to_vec();
+
+    // Only the main thread reports, with the nodes of every thread
+    let is_main_thread = thread_num == 0;
+    let mut best_move: Option<Move> = None;
+
+    let mut positions_processed: u64 = 0;
+    let mut seldepth: u64 = 0;
+    // Kept between iterations, since what caused cutoffs at the last depth likely still does
+    let mut killers = KillerMoves::new();
+    let mut history_table = HistoryTable::new();
+    let mut pv_table = PvTable::new();
+    let mut pv = Vec::new();
+    // Unlike `latest_eval`, only set once an iteration completes
+    let mut eval = Eval::DRAW;
+    let mut lines = Vec::new();
+    let multi_pv = params.multi_pv.unwrap_or(1).max(1);
+    let mut latest_eval = Eval::DRAW;
+
+    'outer: for iteration in 1..=max_depth {
+        // Odd numbered helpers search a ply deeper
+        let iterative_deepening_max_depth = (iteration + thread_num % 2).min(max_depth);
+        let iteration_start_time = Instant::now();
+        debug_span!(
+            "search_iterative_deepening_iteration",
+            depth = iterative_deepening_max_depth
+        );
+        debug!(
+            "Iterative deepening iteration: {} of {}",
+            iterative_deepening_max_depth, max_depth
+        );
+        let iterative_deepening_max_depth: u64 = iterative_deepening_max_depth.try_into().unwrap();
+        seldepth = 0;
+
+        // The eval rarely changes much between iterations, so the root is first searched with a
+        // narrow window around the last one. If the eval falls outside of it, that side is widened
+        // and the root searched again.
+        let prev_eval = latest_eval;
+        let mut delta = ASPIRATION_DELTA;
+        // With several lines, the worse ones would fall outside of a window around the best one.
+        let (mut window_alpha, mut window_beta) = match prev_eval.centipawns() {
+            Some(score) if iterative_deepening_max_depth > 1 && multi_pv == 1 => {
+                (Eval::cp(score - delta), Eval::cp(score + delta))
+            }
+            _ => FULL_WINDOW,
+        };
+
+        // Find value of each move up to current depth
+        let (move_vals, mut move_pvs) = loop {
+            let mut move_vals = HashMap::with_capacity(moves.len());
+            let mut move_pvs = HashMap::with_capacity(moves.len());
+            let mut alpha = window_alpha;
+            // Evals of the best `multi_pv` moves so far, best first. Later moves only have to be
+            // proven worse than the last of them.
+            let mut best_evals: Vec<Eval> = Vec::with_capacity(multi_pv + 1);
+            for (move_num, mve) in moves.iter().copied().enumerate() {
+                history.push(position.zobrist_key());
+                let undo = position.make_move(&mve).unwrap();
+                // Moves after the first `multi_pv` are only searched with a null window, to prove
+                // they're worse. The few that aren't are searched again with the full window.
+                let mut beta = if move_num < multi_pv {
+                    window_beta
+                } else {
+                    alpha
+                };
+                let maybe_move_eval = loop {
+                    let maybe_move_eval = search_helper(
+                        &mut position,
+                        &mut history,
+                        params,
+                        1,
+                        iterative_deepening_max_depth,
+                        &mut positions_processed,
+                        &mut seldepth,
+                        start,
+                        &mut latest_eval,
+                        beta.flip(),
+                        alpha.flip(),
+                        true,
+                        // Extensions can at most double the length of a line
+                        iterative_deepening_max_depth,
+                        move_gen,
+                        position_eval,
+                        transposition_table,
+                        &mut killers,
+                        &mut history_table,
+                        &mut pv_table,
+                        nodes,
+                        is_main_thread,
+                        Arc::clone(&terminate),
+                    );
+                    match maybe_move_eval {
+                        Some(move_eval)
+                            if beta < window_beta
+                                && move_eval.flip() >= alpha
+                                && move_eval.flip() < window_beta =>
+                        {
+                            beta = window_beta
+                        }
+                        _ => break maybe_move_eval,
+                    }
+                };
+                position.unmake_move(&mve, undo);
+                history.pop();
+
+                let Some(move_eval) = maybe_move_eval else {
+                    if is_main_thread {
+                        write_search_info(
+                            iterative_deepening_max_depth,
+                            total_nodes(positions_processed, nodes),
+                            seldepth,
+                            1,
+                            start,
+                            &latest_eval,
+                            Bound::Exact,
+                            &pv,
+                            transposition_table.hashfull(),
+                        );
+                    }
+                    break 'outer;
+                };
+                // Since this is after making a move, flip the value to get the value
+                // relative to the side of `position`
+                let move_eval = move_eval.flip();
+                move_vals.insert(mve, move_eval);
+                if best_evals.len() < multi_pv || move_eval > best_evals[multi_pv - 1] {
+                    pv_table.update(0, mve);
+                    move_pvs.insert(mve, pv_table.line(0).to_vec());
+                    let idx = best_evals.partition_point(|&best| best >= move_eval);
+                    best_evals.insert(idx, move_eval);
+                    best_evals.truncate(multi_pv);
+                    if best_evals.len() == multi_pv && best_evals[multi_pv - 1] > alpha {
+                        alpha = best_evals[multi_pv - 1];
+                    }
+                }
+                if best_evals[0] >= window_beta {
+                    break;
+                }
+            }
+            let best_eval = best_evals.first().copied().unwrap_or(FULL_WINDOW.0);
+
+            delta *= 2;
+            let Some(prev_score) = prev_eval.centipawns() else {
+                break (move_vals, move_pvs);
+            };
+            if best_eval <= window_alpha && window_alpha != FULL_WINDOW.0 {
+                if is_main_thread {
+                    write_search_info(
+                        iterative_deepening_max_depth,
+                        total_nodes(positions_processed, nodes),
+                        seldepth,
+                        1,
+                        start,
+                        &best_eval,
+                        Bound::Upper,
+                        &pv,
+                        transposition_table.hashfull(),
+                    );
+                }
+                // Mates are far outside any window of centipawns
+                window_alpha = if delta > MAX_ASPIRATION_DELTA || best_eval.is_mate() {
+                    FULL_WINDOW.0
+                } else {
+                    Eval::cp(prev_score - delta)
+                };
+            } else if best_eval >= window_beta && window_beta != FULL_WINDOW.1 {
+                if is_main_thread {
+                    write_search_info(
+                        iterative_deepening_max_depth,
+                        total_nodes(positions_processed, nodes),
+                        seldepth,
+                        1,
+                        start,
+                        &best_eval,
+                        Bound::Lower,
+                        pv_table.line(0),
+                        transposition_table.hashfull(),
+                    );
+                }
+                window_beta = if delta > MAX_ASPIRATION_DELTA || best_eval.is_mate() {
+                    FULL_WINDOW.1
+                } else {
+                    Eval::cp(prev_score + delta)
+                };
+            } else {
+                break (move_vals, move_pvs);
+            }
+        };
+
+        // Sort moves by descending value, for this depth. Moves that were only searched with a
+        // null window have upper bounds, which are still below the best move's value, and moves
+        // after one that can't be beaten aren't searched at all.
+        moves.sort_by(|move1, move2| {
+            let val1 = move_vals.get(move1).unwrap_or(&FULL_WINDOW.0);
+            let val2 = move_vals.get(move2).unwrap_or(&FULL_WINDOW.0);
+            val2.cmp(val1)
+        });
+
+        // Find best move
+        best_move = Some(moves[0]);
+
+        latest_eval = move_vals[&best_move.unwrap()];
+        lines = moves[..multi_pv.min(moves.len())]
+            .iter()
+            .map(|mve| PvLine {
+                eval: move_vals[mve],
+                pv: move_pvs.remove(mve).unwrap(),
+            })
+            .collect::<Vec<_>>();
+        eval = latest_eval;
+        pv = lines[0].pv.clone();
+
+        for (line_num, line) in lines.iter().enumerate().filter(|_| is_main_thread) {
+            write_search_info(
+                iterative_deepening_max_depth,
+                total_nodes(positions_processed, nodes),
+                seldepth,
+                line_num + 1,
+                start,
+                &line.eval,
+                Bound::Exact,
+                &line.pv,
+                transposition_table.hashfull(),
+            );
+        }
+
+        debug!("best move: {}, eval: {}", best_move.unwrap(), latest_eval);
+
+        // Nothing is pruned in a mate search, so a mate is proven as soon as it's found
+        if let Some(mate) = params.mate {
+            if latest_eval
+                .mate_moves()
+                .is_some_and(|moves| moves > 0 && moves as u64 <= mate)
+            {
+                debug!("Found mate in {}", latest_eval);
+                break;
+            }
+        }
+
+        if tracing::enabled!(tracing::Level::DEBUG) {
+            for (mve, val) in &move_vals {
+                debug!("{}: {}", mve, val);
+            }
+            debug!("==================================");
+        }
+
+        // Skip if we've elapsed the max amount of time or that we think the next iteration will
+        // definitely go over on time. A mate search runs until it proves or refutes the mate.
+        // Helpers run until the main thread stops them.
+        let elapsed = start.elapsed();
+        if is_main_thread
+            && params.mate.is_none()
+            && (elapsed + iteration_start_time.elapsed()) > time_to_use
+        {
+            debug!(
+                "Search time exceeded time to use: {:?} > {:?}",
+                elapsed, time_to_use
+            );
+            break;
+        }
+        debug!("Time: {:?} < {:?} to use", elapsed, time_to_use);
+    }
+
+    // The rest of this thread's nodes, which didn't make up a whole batch
+    nodes.fetch_add(positions_processed % NODES_PER_BATCH, Ordering::Relaxed);
+
+    let search_info = SearchResultInfo {
+        positions_processed,
+        seldepth,
+        pv,
+        eval,
+        lines,
+        time_elapsed: start.elapsed(),
+    };
+
+    (best_move, search_info)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn search_helper(
+    position: &mut Position,
+    history: &mut Vec<u64>,
+    params: &SearchParams,
+    curr_depth: u64,
+    iterative_deepening_max_depth: u64,
+    positions_processed: &mut u64,
+    seldepth: &mut u64,
+    start_time: &Instant,
+    latest_eval: &mut Eval,
+    mut alpha: Eval,
+    beta: Eval,
+    allow_null_move: bool,
+    extensions_left: u64,
+    move_gen: impl GenerateMoves + std::marker::Copy,
+    position_eval: impl EvaluatePosition + std::marker::Copy,
+    transposition_table: &TranspositionTable,
+    killers: &mut KillerMoves,
+    history_table: &mut HistoryTable,
+    pv_table: &mut PvTable,
+    nodes: &AtomicU64,
+    // Helper threads don't report anything
+    is_main_thread: bool,
+    terminate: Arc<AtomicBool>,
+) -> Option<Eval> {
+    if is_search_stopped(params, *positions_processed, nodes, start_time, &terminate) {
+        return None;
+    }
+    pv_table.clear(curr_depth);
+
+    // Repeating a position is scored as a draw. One repetition is enough, since if repeating is
+    // the best either side can do, it can be repeated again.
+    let is_repetition = count_repetitions(history, position) > 0;
+
+    // Quiescence search counts the node itself
+    if curr_depth == iterative_deepening_max_depth && !is_repetition {
+        return quiescence_search(
+            position,
+            params,
+            curr_depth,
+            positions_processed,
+            seldepth,
+            start_time,
+            alpha,
+            beta,
+            move_gen,
+            position_eval,
+            nodes,
+            &terminate,
+        );
+    }
+
+    count_node(positions_processed, nodes);
+    *seldepth = (*seldepth).max(curr_depth);
+
+    if is_main_thread && *positions_processed % 250_000 == 0 {
+        write_search_info(
+            iterative_deepening_max_depth,
+            total_nodes(*positions_processed, nodes),
+            *seldepth,
+            1,
+            start_time,
+            latest_eval,
+            Bound::Exact,
+            &[],
+            transposition_table.hashfull(),
+        );
+    }
+
+    if is_repetition {
+        return Some(Eval::DRAW);
+    }
+
+    // Generated before probing the transposition table, because the game being over isn't part
+    // of a position's key
+    let moves = move_gen.gen_moves(position);
+    match status_from_moves(position, &moves, move_gen) {
+        GameStatus::Ongoing => (),
+        GameStatus::Checkmate => return Some(Eval::mated_in(curr_depth as u16)),
+        _ => return Some(Eval::DRAW),
+    }
+
+    let depth_left: u8 = (iterative_deepening_max_depth - curr_depth)
+        .try_into()
+        .unwrap();
+    let original_alpha = alpha;
+
+    // Nodes searched with a full window are on the principal variation. They're few, so they're
+    // never cut off by the transposition table, which would cut the principal variation short.
+    let is_pv_node = alpha < beta;
+
+    let tt_entry = transposition_table.probe(position.zobrist_key(), curr_depth);
+    let mut tt_move = None;
+    if let Some(entry) = tt_entry {
+        if entry.depth >= depth_left && !is_pv_node {
+            match entry.bound {
+                Bound::Exact => return Some(entry.eval),
+                Bound::Lower if entry.eval >= beta => return Some(entry.eval),
+                Bound::Upper if entry.eval <= alpha => return Some(entry.eval),
+                _ => (),
+            }
+        }
+        tt_move = entry.best_move;
+    }
+
+    // Null move pruning: if the side to move is still doing well enough for a cutoff after
+    // passing, then any real move is assumed to do at least as well, so the node is pruned
+    // after a reduced search. Passing isn't the worst move in zugzwang, so it isn't tried
+    // without pieces besides pawns, where zugzwang is common, or in check, where it's illegal.
+    // It's also not tried twice in a row, which would just be the same position with a
+    // shallower search.
+    let side = position.state.to_move;
+    let in_check = !move_gen.gen_checkers(position).is_empty();
+    // Pruning can miss mates, so a mate search searches every move to the full depth
+    let is_mate_search = params.mate.is_some();
+    // Only needed to decide what to prune, which never happens on the principal variation, in
+    // check or in a mate search
+    let static_eval = (!is_pv_node && !in_check && !is_mate_search)
+        .then(|| position_eval.evaluate(position, move_gen));
+    if allow_null_move
+        && depth_left >= NULL_MOVE_MIN_DEPTH
+        && position.has_non_pawn_material(side)
+        && static_eval.is_some_and(|static_eval| static_eval >= beta)
+    {
+        let reduction: u64 = if depth_left > NULL_MOVE_DEEP_REDUCTION_DEPTH {
+            3
+        } else {
+            2
+        };
+
+        history.push(position.zobrist_key());
+        let undo = position.make_null_move();
+        let null_move_eval = search_helper(
+            position,
+            history,
+            params,
+            curr_depth + 1,
+            iterative_deepening_max_depth - reduction,
+            positions_processed,
+            seldepth,
+            start_time,
+            latest_eval,
+            beta.flip(),
+            beta.flip(),
+            false,
+            extensions_left,
+            move_gen,
+            position_eval,
+            transposition_table,
+            killers,
+            history_table,
+            pv_table,
+            nodes,
+            is_main_thread,
+            Arc::clone(&terminate),
+        );
+        position.unmake_null_move(undo);
+        history.pop();
+
+        let null_move_eval = null_move_eval?.flip();
+        if null_move_eval >= beta {
+            // Searching the position itself without null moves, to the same reduced depth,
+            // catches most zugzwangs at a fraction of the cost of a full search
+            let verified = !params.null_move_verification
+                || search_helper(
+                    position,
+                    history,
+                    params,
+                    curr_depth,
+                    iterative_deepening_max_depth - reduction,
+                    positions_processed,
+                    seldepth,
+                    start_time,
+                    latest_eval,
+                    beta,
+                    beta,
+                    false,
+                    extensions_left,
+                    move_gen,
+                    position_eval,
+                    transposition_table,
+                    killers,
+                    history_table,
+                    pv_table,
+                    nodes,
+                    is_main_thread,
+                    Arc::clone(&terminate),
+                )? >= beta;
+            if verified {
+                // Mates found after passing aren't proven, so they aren't returned. Otherwise
+                // the eval is returned rather than `beta`, since with a null window a parent
+                // can't tell `beta` apart from a fail high, and would search again.
+                let eval = if null_move_eval.is_mate() {
+                    beta
+                } else {
+                    null_move_eval
+                };
+                transposition_table.store(
+                    position.zobrist_key(),
+                    depth_left,
+                    curr_depth,
+                    Bound::Lower,
+                    eval,
+                    tt_move,
+                );
+                return Some(eval);
+            }
+        }
+    }
+
+    // Singular extensions: if the transposition table move is much better than every other move,
+    // the position hinges on it, so it's searched deeper. Whether it is, is checked with a
+    // reduced null window search of every other move against an eval a margin below its own.
+    let mut is_tt_move_singular = false;
+    if let Some(TranspositionTableEntry {
+        depth,
+        bound: Bound::Lower | Bound::Exact,
+        eval: tt_eval,
+        best_move: Some(tt_move),
+        ..
+    }) = tt_entry
+    {
+        // Mates have no margin to be worse by
+        if let Some(tt_score) = tt_eval.centipawns().filter(|_| {
+            extensions_left > 0
+                && depth_left >= SINGULAR_EXTENSION_MIN_DEPTH
+                && depth + SINGULAR_EXTENSION_MAX_TT_DEPTH_DIFF >= depth_left
+                && moves.contains(&tt_move)
+        }) {
+            let singular_beta = singular_beta(tt_score, depth_left);
+            is_tt_move_singular = true;
+            for mve in moves.iter().filter(|&&mve| mve != tt_move) {
+                history.push(position.zobrist_key());
+                let undo = position.make_move(mve).unwrap();
+                let eval = search_helper(
+                    position,
+                    history,
+                    params,
+                    curr_depth + 1,
+                    curr_depth + u64::from(depth_left / 2),
+                    positions_processed,
+                    seldepth,
+                    start_time,
+                    latest_eval,
+                    singular_beta.flip(),
+                    singular_beta.flip(),
+                    true,
+                    extensions_left,
+                    move_gen,
+                    position_eval,
+                    transposition_table,
+                    killers,
+                    history_table,
+                    pv_table,
+                    nodes,
+                    is_main_thread,
+                    Arc::clone(&terminate),
+                );
+                position.unmake_move(mve, undo);
+                history.pop();
+                if eval?.flip() >= singular_beta {
+                    is_tt_move_singular = false;
+                    break;
+                }
+            }
+        }
+    }
+
+    // With only one legal move, the position is forced, so the move is extended
+    let num_moves = moves.len();
+
+    // The best move found by a previous search of this position goes first, since it is the
+    // most likely to cause a cutoff
+    let move_picker = MovePicker::new(
+        position,
+        moves,
+        tt_move,
+        killers.get(curr_depth),
+        history_table,
+        move_gen,
+    );
+
+    // Quiet moves can't raise the eval by much, so if even a generous margin on top of the static
+    // eval doesn't reach alpha, they're not worth searching this close to the horizon
+    let futility_eval = match static_eval.and_then(Eval::centipawns) {
+        Some(score) if depth_left <= MAX_PRUNING_DEPTH => {
+            Some(Eval::cp(score + futility_margin(depth_left)))
+        }
+        _ => None,
+    };
+    let is_futile = futility_eval.is_some_and(|futility_eval| futility_eval <= alpha);
+    // Late move pruning is only done when the node is already expected to fail low, so the
+    // quiet moves are assumed to be worth at most the static eval, or the futility eval when
+    // that's below alpha too
+    let pruned_eval = if is_futile {
+        futility_eval
+    } else {
+        static_eval.filter(|&static_eval| depth_left <= MAX_PRUNING_DEPTH && static_eval < alpha)
+    };
+
+    let node_killers = killers.get(curr_depth);
+    let mut quiets_searched = MoveList::new();
+    let mut best_eval = FULL_WINDOW.0;
+    let mut best_move = None;
+    for (move_num, mve) in move_picker.enumerate() {
+        let quiet = is_quiet(position, &mve);
+        // Killers are quiet, but as likely to cause a cutoff as a good capture
+        let is_late_quiet = quiet && !node_killers.contains(&Some(mve));
+        history.push(position.zobrist_key());
+        let move_res = position.make_move(&mve);
+        let undo = match move_res {
+            Ok(undo) => undo,
+            Err(err) => {
+                if is_main_thread {
+                    write_search_info(
+                        iterative_deepening_max_depth,
+                        total_nodes(*positions_processed, nodes),
+                        *seldepth,
+                        1,
+                        start_time,
+                        latest_eval,
+                        Bound::Exact,
+                        &[],
+                        transposition_table.hashfull(),
+                    );
+                }
+                error!("Error for move {}: {}", mve, err);
+                panic!("Err encountered searching, exiting");
+            }
+        };
+        let gives_check = !move_gen.gen_checkers(position).is_empty();
+
+        // Checks, forced replies, pawns about to promote and singular moves are searched a ply
+        // deeper, while the line has extensions left
+        let extension = u64::from(
+            extensions_left > 0
+                && curr_depth + 1 < MAX_SEARCH_DEPTH
+                && (gives_check
+                    || num_moves == 1
+                    || is_pawn_push_to_seventh(position, side, &mve)
+                    || (is_tt_move_singular && tt_move == Some(mve))),
+        );
+
+        // Late move pruning and futility pruning. At least one move is always searched, so
+        // there's an eval to return, and moves that give check are always searched, since they
+        // can be much better than they look.
+        if let Some(pruned_eval) = pruned_eval.filter(|_| {
+            move_num > 0
+                && is_late_quiet
+                && !gives_check
+                && extension == 0
+                && (is_futile || move_num >= late_move_pruning_count(depth_left))
+        }) {
+            position.unmake_move(&mve, undo);
+            history.pop();
+            // Otherwise a pruned node would return the eval of whatever few moves it searched,
+            // which can be far below the pruned moves, and the parent would search again
+            if pruned_eval > best_eval {
+                best_eval = pruned_eval;
+            }
+            continue;
+        }
+
+        // Late move reductions: quiet moves late in the move ordering are searched to a reduced
+        // depth first, and only searched to the full depth if that fails high
+        let mut reduction = if depth_left >= LATE_MOVE_REDUCTION_MIN_DEPTH
+            && move_num >= LATE_MOVE_REDUCTION_MIN_MOVES
+            && is_late_quiet
+            && extension == 0
+            && !in_check
+            && !gives_check
+            && !is_mate_search
+        {
+            let reduction = late_move_reduction(depth_left, move_num);
+            // Moves on the principal variation matter most, so are reduced less
+            if is_pv_node {
+                reduction.saturating_sub(1)
+            } else {
+                reduction
+            }
+        } else {
+            0
+        };
+
+        // Principal variation search: with good move ordering the first move is the best, so
+        // the rest are only searched with a null window, to prove they're worse. The few that
+        // aren't are searched again with the full window. In a node that's already being
+        // searched with a null window, there's nothing to search again.
+        let mut child_beta = if move_num == 0 { beta } else { alpha };
+        let got_eval = loop {
+            let got_eval = search_helper(
+                position,
+                history,
+                params,
+                curr_depth + 1,
+                iterative_deepening_max_depth + extension - u64::from(reduction),
+                positions_processed,
+                seldepth,
+                start_time,
+                latest_eval,
+                child_beta.flip(),
+                alpha.flip(),
+                true,
+                extensions_left - extension,
+                move_gen,
+                position_eval,
+                transposition_table,
+                killers,
+                history_table,
+                pv_table,
+                nodes,
+                is_main_thread,
+                Arc::clone(&terminate),
+            );
+            match got_eval {
+                Some(eval) if reduction > 0 && eval.flip() >= alpha => reduction = 0,
+                Some(eval) if child_beta < beta && eval.flip() >= alpha && eval.flip() < beta => {
+                    child_beta = beta
+                }
+                _ => break got_eval,
+            }
+        };
+        position.unmake_move(&mve, undo);
+        history.pop();
+
+        // Reason for `?`: if the child node is signaling search is terminated,
+        // better terminate self.
+        let got_eval = got_eval?;
+
+        // Then, flip value because it was relative to the other side
+        let got_eval = got_eval.flip();
+
+        if got_eval >= best_eval {
+            best_eval = got_eval;
+            best_move = Some(mve);
+            pv_table.update(curr_depth, mve);
+            if got_eval >= alpha {
+                alpha = got_eval;
+            }
+            *latest_eval = got_eval;
+        }
+
+        // Rather than `alpha >= beta`, so that null windows, where they start out equal, work
+        if best_eval >= beta {
+            // Captures are already ordered well without these
+            if quiet {
+                killers.store(curr_depth, mve);
+                let bonus = i32::from(depth_left) * i32::from(depth_left);
+                history_table.update(side, &mve, bonus);
+                for quiet_searched in &quiets_searched {
+                    history_table.update(side, quiet_searched, -bonus);
+                }
+            }
+            break;
+        }
+        if quiet {
+            quiets_searched.push(mve);
+        }
+    }
+
+    // Checked in this order, since with a null window `original_alpha` and `beta` are equal
+    let bound = if best_eval >= beta {
+        Bound::Lower
+    } else if best_eval <= original_alpha {
+        Bound::Upper
+    } else {
+        Bound::Exact
+    };
+    transposition_table.store(
+        position.zobrist_key(),
+        depth_left,
+        curr_depth,
+        bound,
+        best_eval,
+        best_move,
+    );
+
+    Some(best_eval)
+}
+
+/// Whether the search has to stop early, because it was terminated or ran out of nodes or time.
+fn is_search_stopped(
+    params: &SearchParams,
+    positions_processed: u64,
+    nodes: &AtomicU64,
+    start_time: &Instant,
+    terminate: &AtomicBool,
+) -> bool {
+    if terminate.load(std::sync::atomic::Ordering::Relaxed) {
+        return true;
+    }
+    if let Some(max_nodes) = params.max_nodes {
+        // Every thread stops once the nodes of all of them reach the limit. The other threads'
+        // nodes are only counted once they make up a batch, so they can go over it by less than
+        // a batch each.
+        if total_nodes(positions_processed, nodes) >= max_nodes {
+            return true;
+        }
+    }
+    if let Some(move_time) = params.move_time {
+        if start_time.elapsed() >= move_time {
+            return true;
+        }
+    }
+    false
+}
+
+/// Counts a node searched by this thread, adding a batch of them to the nodes of every thread once
+/// there are enough.
+fn count_node(positions_processed: &mut u64, nodes: &AtomicU64) {
+    *positions_processed += 1;
+    if positions_processed.is_multiple_of(NODES_PER_BATCH) {
+        nodes.fetch_add(NODES_PER_BATCH, Ordering::Relaxed);
+    }
+}
+
+/// Nodes searched by every thread, counting only whole batches of the other threads' nodes.
+fn total_nodes(positions_processed: u64, nodes: &AtomicU64) -> u64 {
+    nodes.load(Ordering::Relaxed) + positions_processed % NODES_PER_BATCH
+}
+
+/// Searches captures and promotions until the position is quiet, so that positions aren't
+/// evaluated in the middle of an exchange. All moves are searched when in check, since standing
+/// pat isn't an option then.
+#[allow(clippy::too_many_arguments)]
+fn quiescence_search(
+    position: &mut Position,
+    params: &SearchParams,
+    curr_depth: u64,
+    positions_processed: &mut u64,
+    seldepth: &mut u64,
+    start_time: &Instant,
+    mut alpha: Eval,
+    beta: Eval,
+    move_gen: impl GenerateMoves + std::marker::Copy,
+    position_eval: impl EvaluatePosition + std::marker::Copy,
+    nodes: &AtomicU64,
+    terminate: &AtomicBool,
+) -> Option<Eval> {
+    if is_search_stopped(params, *positions_processed, nodes, start_time, terminate) {
+        return None;
+    }
+    count_node(positions_processed, nodes);
+    *seldepth = (*seldepth).max(curr_depth);
+
+    if curr_depth >= MAX_SEARCH_DEPTH {
+        return Some(
+            position_eval
+                .evaluate(position, move_gen)
+                .to_root_relative(curr_depth),
+        );
+    }
+
+    let in_check = !move_gen.gen_checkers(position).is_empty();
+    let (mut best_eval, moves) = if in_check {
+        let evasions = move_gen.gen_moves_of_type(position, GenType::Evasions);
+        let mated = Eval::mated_in(curr_depth as u16);
+        if evasions.is_empty() {
+            return Some(mated);
+        }
+        (mated, evasions)
+    } else {
+        // The side to move can usually do at least as well as the static evaluation by not
+        // capturing anything, so that's a lower bound
+        // Captures reset the fifty move counter, so they can't be searched past a draw by it
+        let stand_pat = position_eval.evaluate(position, move_gen);
+        if stand_pat >= beta || position.is_fifty_move_draw() {
+            return Some(stand_pat);
+        }
+        if stand_pat > alpha {
+            alpha = stand_pat;
+        }
+
+        let mut captures = move_gen.gen_moves_of_type(position, GenType::Captures);
+        // Under promotions are almost never better than promoting to a queen, and captures that
+        // lose material can't raise alpha above the stand pat
+        captures.retain(|mve| {
+            matches!(mve.promotion, None | Some(Piece::Queen)) && move_gen.see_ge(position, mve, 0)
+        });
+        (stand_pat, captures)
+    };
+
+    for mve in MovePicker::captures(position, moves, move_gen) {
+        let undo = position.make_move(&mve).unwrap();
+        let got_eval = quiescence_search(
+            position,
+            params,
+            curr_depth + 1,
+            positions_processed,
+            seldepth,
+            start_time,
+            beta.flip(),
+            alpha.flip(),
+            move_gen,
+            position_eval,
+            nodes,
+            terminate,
+        );
+        position.unmake_move(&mve, undo);
+        let got_eval = got_eval?.flip();
+
+        if got_eval > best_eval {
+            best_eval = got_eval;
+            if got_eval > alpha {
+                alpha = got_eval;
+            }
+        }
+        if best_eval >= beta {
+            break;
+        }
+    }
+
+    Some(best_eval)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn write_search_info(
+    iterative_deepening_max_depth: u64,
+    nodes_processed: u64,
+    seldepth: u64,
+    multi_pv: usize,
+    start_time: &Instant,
+    latest_eval: &Eval,
+    bound: Bound,
+    pv: &[Move],
+    hashfull: u16,
+) {
+    let nps = nodes_processed as f32 / start_time.elapsed().as_secs_f32();
+    let bound = match bound {
+        Bound::Exact => "",
+        Bound::Lower => " lowerbound",
+        Bound::Upper => " upperbound",
+    };
+    info!("info depth {} seldepth {} multipv {} score {}{} nodes {} nps {:.0} hashfull {} tbhits {} time {} pv {}", iterative_deepening_max_depth, seldepth, multi_pv, latest_eval, bound, nodes_processed, nps, hashfull, 0, start_time.elapsed().as_millis(), moves_to_string(pv).to_ascii_lowercase());
+}
@@ -0,0 +1,287 @@
+use arrayvec::ArrayVec;
+
+use crate::move_gen::{GenerateMoves, MoveList, MAX_MOVES};
+use crate::position::{Move, Piece, Position, Side};
+
+use super::MAX_SEARCH_DEPTH;
+
+// Moves are searched in bands, and within a band by their own score:
+// 1. The transposition table move
+// 2. Captures and promotions that don't lose material, most valuable victim first
+// 3. Killer moves
+// 4. Quiet moves, by history
+// 5. Captures that lose material
+const TT_MOVE_SCORE: i32 = 4_000_000;
+const GOOD_CAPTURE_SCORE: i32 = 3_000_000;
+const KILLER_SCORE: i32 = 2_000_000;
+const BAD_CAPTURE_SCORE: i32 = -3_000_000;
+
+/// History scores stay within plus or minus this, so they never leave the quiet moves' band.
+const MAX_HISTORY: i32 = 16_384;
+
+/// The piece `mve` captures, if any.
+fn captured_piece(position: &Position, mve: &Move) -> Option<Piece> {
+    match position.is_piece_at(mve.dest) {
+        Some((piece, _)) => Some(piece),
+        None if position.state.en_passant_target == Some(mve.dest)
+            && position
+                .piece_occupancy(Piece::Pawn, position.state.to_move)
+                .is_square_set(mve.src) =>
+        {
+            Some(Piece::Pawn)
+        }
+        None => None,
+    }
+}
+
+/// Whether `mve` neither captures nor promotes.
+pub(crate) fn is_quiet(position: &Position, mve: &Move) -> bool {
+    mve.promotion.is_none() && captured_piece(position, mve).is_none()
+}
+
+/// Most valuable victim, least valuable attacker. Promotions count the promoted piece as a
+/// victim.
+fn mvv_lva(position: &Position, mve: &Move) -> i32 {
+    let (attacker, _) = position
+        .is_piece_at(mve.src)
+        .expect("move should start on a piece");
+    let victim = captured_piece(position, mve).map_or(0, |piece| piece as i32 + 1);
+    let promotion = mve.promotion.map_or(0, |piece| piece as i32);
+
+    (victim + promotion) * 8 - attacker as i32
+}
+
+/// Up to two quiet moves per ply that caused a beta cutoff, which are likely to cause one in
+/// sibling nodes too.
+pub(crate) struct KillerMoves {
+    moves: [[Option<Move>; 2]; MAX_SEARCH_DEPTH as usize],
+}
+
+impl KillerMoves {
+    pub(crate) fn new() -> Self {
+        Self {
+            moves: [[None; 2]; MAX_SEARCH_DEPTH as usize],
+        }
+    }
+
+    pub(crate) fn get(&self, ply: u64) -> [Option<Move>; 2] {
+        self.moves.get(ply as usize).copied().unwrap_or_default()
+    }
+
+    pub(crate) fn store(&mut self, ply: u64, mve: Move) {
+        let Some(killers) = self.moves.get_mut(ply as usize) else {
+            return;
+        };
+        if killers[0] != Some(mve) {
+            killers[1] = killers[0];
+            killers[0] = Some(mve);
+        }
+    }
+}
+
+/// Scores quiet moves by how often they caused a beta cutoff, indexed by side, source square and
+/// destination square (a butterfly table). Shared by every node of the search.
+pub(crate) struct HistoryTable {
+    scores: [[[i32; 64]; 64]; 2],
+}
+
+impl HistoryTable {
+    pub(crate) fn new() -> Self {
+        Self {
+            scores: [[[0; 64]; 64]; 2],
+        }
+    }
+
+    fn score_mut(&mut self, side: Side, mve: &Move) -> &mut i32 {
+        &mut self.scores[side as usize][mve.src as usize][mve.dest as usize]
+    }
+
+    pub(crate) fn get(&self, side: Side, mve: &Move) -> i32 {
+        self.scores[side as usize][mve.src as usize][mve.dest as usize]
+    }
+
+    /// Adds `bonus`, which is negative for moves that didn't cause a cutoff. Scores close to
+    /// the limit move less, so they stay in range and old results fade out.
+    pub(crate) fn update(&mut self, side: Side, mve: &Move, bonus: i32) {
+        let bonus = bonus.clamp(-MAX_HISTORY, MAX_HISTORY);
+        let score = self.score_mut(side, mve);
+        *score += bonus - *score * bonus.abs() / MAX_HISTORY;
+    }
+}
+
+/// Hands out moves best first, by picking the highest scored move that's left each time. Cheaper
+/// than sorting up front, since after a cutoff the rest of the moves are never needed.
+pub(crate) struct MovePicker {
+    moves: MoveList,
+    scores: ArrayVec<i32, MAX_MOVES>,
+    next: usize,
+}
+
+impl MovePicker {
+    pub(crate) fn new(
+        position: &Position,
+        moves: MoveList,
+        tt_move: Option<Move>,
+        killers: [Option<Move>; 2],
+        history: &HistoryTable,
+        move_gen: impl GenerateMoves,
+    ) -> Self {
+        let side = position.state.to_move;
+        let scores = moves
+            .iter()
+            .map(|mve| {
+                if Some(*mve) == tt_move {
+                    TT_MOVE_SCORE
+                } else if !is_quiet(position, mve) {
+                    score_capture(position, mve, &move_gen)
+                } else if Some(*mve) == killers[0] {
+                    KILLER_SCORE + 1
+                } else if Some(*mve) == killers[1] {
+                    KILLER_SCORE
+                } else {
+                    history.get(side, mve)
+                }
+            })
+            .collect();
+
+        Self {
+            moves,
+            scores,
+            next: 0,
+        }
+    }
+
+    /// For quiescence search, which only searches captures and promotions, or evasions when in
+    /// check.
+    pub(crate) fn captures(
+        position: &Position,
+        moves: MoveList,
+        move_gen: impl GenerateMoves,
+    ) -> Self {
+        let scores = moves
+            .iter()
+            .map(|mve| score_capture(position, mve, &move_gen))
+            .collect();
+
+        Self {
+            moves,
+            scores,
+            next: 0,
+        }
+    }
+}
+
+fn score_capture(position: &Position, mve: &Move, move_gen: &impl GenerateMoves) -> i32 {
+    if move_gen.see_ge(position, mve, 0) {
+        GOOD_CAPTURE_SCORE + mvv_lva(position, mve)
+    } else {
+        BAD_CAPTURE_SCORE + mvv_lva(position, mve)
+    }
+}
+
+impl Iterator for MovePicker {
+    type Item = Move;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let best_idx = (self.next..self.moves.len()).max_by_key(|&idx| self.scores[idx])?;
+        self.moves.swap(self.next, best_idx);
+        self.scores.swap(self.next, best_idx);
+        self.next += 1;
+        Some(self.moves[self.next - 1])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitboard::Square::*;
+    use crate::move_gen::{GenType, MAGIC_BITBOARD_MOVE_GEN};
+    use testresult::TestResult;
+
+    #[test]
+    fn test_move_picker_order() -> TestResult {
+        // White can win the queen three ways, or lose the knight to the pawn on c6
+        let position = Position::from_fen("4k3/8/2p5/1p1q4/4P3/2N5/8/3RK3 w - - 0 1")?;
+        let move_gen = MAGIC_BITBOARD_MOVE_GEN;
+        let tt_move = Move::new(E1, F2);
+        let killer = Move::new(D1, B1);
+        let mut history = HistoryTable::new();
+        history.update(Side::White, &Move::new(D1, A1), 500);
+
+        let picked: Vec<Move> = MovePicker::new(
+            &position,
+            move_gen.gen_moves(&position),
+            Some(tt_move),
+            [Some(killer), None],
+            &history,
+            move_gen,
+        )
+        .collect();
+
+        assert_eq!(picked.len(), move_gen.gen_moves(&position).len());
+        assert_eq!(
+            picked[..6],
+            [
+                tt_move,
+                Move::new(E4, D5),
+                Move::new(C3, D5),
+                Move::new(D1, D5),
+                killer,
+                Move::new(D1, A1),
+            ]
+        );
+        assert_eq!(picked.last(), Some(&Move::new(C3, B5)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_move_picker_captures() -> TestResult {
+        let position = Position::from_fen("4k3/8/8/3q1r2/4P3/8/8/4K3 w - - 0 1")?;
+        let move_gen = MAGIC_BITBOARD_MOVE_GEN;
+
+        let picked: Vec<Move> = MovePicker::captures(
+            &position,
+            move_gen.gen_moves_of_type(&position, GenType::Captures),
+            move_gen,
+        )
+        .collect();
+
+        assert_eq!(picked, [Move::new(E4, D5), Move::new(E4, F5)]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_killer_moves() {
+        let mut killers = KillerMoves::new();
+        let (first, second) = (Move::new(E2, E4), Move::new(D2, D4));
+
+        killers.store(3, first);
+        killers.store(3, first);
+        assert_eq!(killers.get(3), [Some(first), None]);
+
+        killers.store(3, second);
+        assert_eq!(killers.get(3), [Some(second), Some(first)]);
+        assert_eq!(killers.get(2), [None, None]);
+
+        // Plies past the end are ignored rather than panicking
+        killers.store(MAX_SEARCH_DEPTH, first);
+        assert_eq!(killers.get(MAX_SEARCH_DEPTH), [None, None]);
+    }
+
+    #[test]
+    fn test_history_stays_in_range() {
+        let mut history = HistoryTable::new();
+        let mve = Move::new(E2, E4);
+
+        for _ in 0..1000 {
+            history.update(Side::White, &mve, 10_000);
+        }
+        assert!(history.get(Side::White, &mve) <= MAX_HISTORY);
+        assert_eq!(history.get(Side::Black, &mve), 0);
+
+        for _ in 0..1000 {
+            history.update(Side::White, &mve, -10_000);
+        }
+        assert!(history.get(Side::White, &mve) >= -MAX_HISTORY);
+    }
+}
@@ -0,0 +1,86 @@
+use std::sync::LazyLock;
+
+use crate::move_gen::MAX_MOVES;
+
+use super::MAX_SEARCH_DEPTH;
+
+// Source: https://www.chessprogramming.org/Late_Move_Reductions
+//
+// With good move ordering, moves late in the list rarely turn out to be the best, so they're
+// searched to a reduced depth, and only searched again at full depth if that fails high. The
+// deeper the search and the later the move, the more it's reduced.
+
+/// Only moves after this many are reduced, since the earlier ones are the most likely to be best.
+pub(crate) const LATE_MOVE_REDUCTION_MIN_MOVES: usize = 3;
+/// Moves are only reduced with at least this many plies left.
+pub(crate) const LATE_MOVE_REDUCTION_MIN_DEPTH: u8 = 3;
+
+/// Late move pruning and futility pruning only happen with at most this many plies left, where
+/// their errors are the cheapest.
+pub(crate) const MAX_PRUNING_DEPTH: u8 = 3;
+
+/// Centipawns a quiet move has to be able to gain per ply left, for it to be worth searching
+/// when the static eval is below alpha.
+const FUTILITY_MARGIN_PER_PLY: i32 = 150;
+
+struct Reductions([[u8; MAX_MOVES]; MAX_SEARCH_DEPTH as usize]);
+
+static REDUCTIONS: LazyLock<Reductions> = LazyLock::new(calc_reductions);
+
+fn calc_reductions() -> Reductions {
+    let mut reductions = [[0; MAX_MOVES]; MAX_SEARCH_DEPTH as usize];
+    for (depth_left, depth_reductions) in reductions.iter_mut().enumerate().skip(1) {
+        for (move_num, reduction) in depth_reductions.iter_mut().enumerate().skip(1) {
+            *reduction =
+                (0.75 + (depth_left as f64).ln() * (move_num as f64).ln() / 2.25).floor() as u8;
+        }
+    }
+    Reductions(reductions)
+}
+
+/// Plies to reduce the search of the `move_num`th move (from 0) by, with `depth_left` plies
+/// left. Always leaves at least the move itself to search.
+pub(crate) fn late_move_reduction(depth_left: u8, move_num: usize) -> u8 {
+    let depth_idx = usize::from(depth_left).min(MAX_SEARCH_DEPTH as usize - 1);
+    let move_idx = move_num.min(MAX_MOVES - 1);
+    REDUCTIONS.0[depth_idx][move_idx].min(depth_left.saturating_sub(1))
+}
+
+/// How many moves are searched with `depth_left` plies left, before the remaining quiet moves
+/// are pruned.
+pub(crate) fn late_move_pruning_count(depth_left: u8) -> usize {
+    3 + usize::from(depth_left) * usize::from(depth_left)
+}
+
+/// How far below alpha the static eval can be with `depth_left` plies left, before quiet moves
+/// are assumed to be unable to raise it above alpha.
+pub(crate) fn futility_margin(depth_left: u8) -> i32 {
+    FUTILITY_MARGIN_PER_PLY * i32::from(depth_left)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_late_move_reduction() {
+        // Grows with both depth and move number
+        for depth_left in 1..MAX_SEARCH_DEPTH as u8 {
+            for move_num in 1..MAX_MOVES {
+                let reduction = late_move_reduction(depth_left, move_num);
+                assert!(reduction < depth_left.max(1));
+                assert!(reduction >= late_move_reduction(depth_left - 1, move_num));
+                assert!(reduction >= late_move_reduction(depth_left, move_num - 1));
+            }
+        }
+        assert_eq!(late_move_reduction(3, 0), 0);
+        assert_eq!(late_move_reduction(1, 40), 0);
+        assert!(late_move_reduction(10, 30) >= 3);
+
+        // Out of range arguments are clamped rather than panicking
+        assert_eq!(
+            late_move_reduction(u8::MAX, MAX_MOVES),
+            late_move_reduction(MAX_SEARCH_DEPTH as u8 - 1, MAX_MOVES - 1)
+        );
+    }
+}
@@ -0,0 +1,84 @@
+use arrayvec::ArrayVec;
+
+use crate::position::Move;
+
+use super::MAX_SEARCH_DEPTH;
+
+const MAX_PLIES: usize = MAX_SEARCH_DEPTH as usize + 1;
+
+type Line = ArrayVec<Move, MAX_PLIES>;
+
+// Source: https://www.chessprogramming.org/Triangular_PV-Table
+//
+// Each ply keeps the best line found from it so far. When a move becomes the best at a ply, its
+// line is that move followed by the line the child ply just found. A ply's line is cleared when
+// it's entered, so lines cut short by the transposition table, quiescence search or the end of
+// the game don't pick up moves from a sibling's search.
+
+/// The principal variation from every ply of the current search path.
+pub(crate) struct PvTable {
+    lines: Vec<Line>,
+}
+
+impl PvTable {
+    pub(crate) fn new() -> Self {
+        Self {
+            lines: vec![Line::new(); MAX_PLIES],
+        }
+    }
+
+    pub(crate) fn clear(&mut self, ply: u64) {
+        if let Some(line) = self.lines.get_mut(ply as usize) {
+            line.clear();
+        }
+    }
+
+    /// Makes `mve` followed by the line from the next ply the line from `ply`. Plies past the end
+    /// are ignored.
+    pub(crate) fn update(&mut self, ply: u64, mve: Move) {
+        let ply = ply as usize;
+        if ply >= MAX_PLIES {
+            return;
+        }
+        let (lines, child_lines) = self.lines.split_at_mut(ply + 1);
+        let line = &mut lines[ply];
+        line.clear();
+        line.push(mve);
+        if let Some(child_line) = child_lines.first() {
+            line.extend(child_line.iter().copied().take(MAX_PLIES - 1));
+        }
+    }
+
+    pub(crate) fn line(&self, ply: u64) -> &[Move] {
+        self.lines.get(ply as usize).map_or(&[], |line| line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitboard::Square::*;
+
+    #[test]
+    fn test_pv_table() {
+        let mut pv_table = PvTable::new();
+        let (e4, e5, nf3) = (Move::new(E2, E4), Move::new(E7, E5), Move::new(G1, F3));
+
+        pv_table.clear(2);
+        pv_table.update(2, nf3);
+        pv_table.update(1, e5);
+        pv_table.update(0, e4);
+        assert_eq!(pv_table.line(0), [e4, e5, nf3]);
+        assert_eq!(pv_table.line(1), [e5, nf3]);
+
+        // A new best move at ply 1 whose child line was cut short
+        pv_table.clear(2);
+        pv_table.update(1, Move::new(D7, D5));
+        assert_eq!(pv_table.line(1), [Move::new(D7, D5)]);
+
+        pv_table.update(MAX_SEARCH_DEPTH, e4);
+        assert_eq!(pv_table.line(MAX_SEARCH_DEPTH), [e4]);
+        pv_table.update(MAX_SEARCH_DEPTH + 1, e4);
+        assert!(pv_table.line(MAX_SEARCH_DEPTH + 1).is_empty());
+    }
+}
@@ -0,0 +1,317 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::{fmt, mem};
+
+use crate::bitboard::Square;
+use crate::evaluation::Eval;
+use crate::position::{Move, Piece};
+
+pub const DEFAULT_TRANSPOSITION_TABLE_SIZE_MB: usize = 16;
+
+/// How the stored eval relates to the real value of the position, which depends on whether the
+/// search of the position was cut off by alpha-beta pruning.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Bound {
+    Exact,
+    /// Real value is at least the stored eval (the search failed high)
+    Lower,
+    /// Real value is at most the stored eval (the search failed low)
+    Upper,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct TranspositionTableEntry {
+    pub(crate) key: u64,
+    pub(crate) depth: u8,
+    pub(crate) bound: Bound,
+    pub(crate) eval: Eval,
+    pub(crate) best_move: Option<Move>,
+}
+
+// Layout of an entry packed into 64 bits, besides its key
+const EVAL_SHIFT: u64 = 0;
+const DEPTH_SHIFT: u64 = 16;
+const BOUND_SHIFT: u64 = 24;
+const SRC_SHIFT: u64 = 26;
+const DEST_SHIFT: u64 = 32;
+/// 0 for no promotion, otherwise the piece plus 1
+const PROMOTION_SHIFT: u64 = 38;
+const HAS_MOVE_BIT: u64 = 1 << 41;
+/// Set in every stored entry, so an empty slot can't be mistaken for an entry with key 0
+const OCCUPIED_BIT: u64 = 1 << 42;
+
+impl TranspositionTableEntry {
+    fn to_data(self) -> u64 {
+        let bound = match self.bound {
+            Bound::Exact => 0,
+            Bound::Lower => 1,
+            Bound::Upper => 2,
+        };
+        let mve = self.best_move.map_or(0, |mve| {
+            HAS_MOVE_BIT
+                | (mve.src as u64) << SRC_SHIFT
+                | (mve.dest as u64) << DEST_SHIFT
+                | mve.promotion.map_or(0, |piece| piece as u64 + 1) << PROMOTION_SHIFT
+        });
+        OCCUPIED_BIT
+            | u64::from(self.eval.to_bits()) << EVAL_SHIFT
+            | u64::from(self.depth) << DEPTH_SHIFT
+            | bound << BOUND_SHIFT
+            | mve
+    }
+
+    fn from_data(key: u64, data: u64) -> Self {
+        let bound = match (data >> BOUND_SHIFT) & 0b11 {
+            0 => Bound::Exact,
+            1 => Bound::Lower,
+            _ => Bound::Upper,
+        };
+        let best_move = (data & HAS_MOVE_BIT != 0).then(|| Move {
+            src: Square::from_u8((data >> SRC_SHIFT) as u8 & 0b11_1111),
+            dest: Square::from_u8((data >> DEST_SHIFT) as u8 & 0b11_1111),
+            promotion: match (data >> PROMOTION_SHIFT) & 0b111 {
+                0 => None,
+                piece => Piece::from_repr(piece as usize - 1),
+            },
+        });
+        Self {
+            key,
+            depth: (data >> DEPTH_SHIFT) as u8,
+            bound,
+            eval: Eval::from_bits((data >> EVAL_SHIFT) as u16),
+            best_move,
+        }
+    }
+}
+
+/// An entry packed into two words that can each be read and written atomically. The key is
+/// stored xored with the data, so that if another thread wrote the slot between reading the two,
+/// the key doesn't match and the torn entry is ignored.
+///
+/// Source: https://www.chessprogramming.org/Shared_Hash_Table#Lockless
+struct Slot {
+    key_xor_data: AtomicU64,
+    data: AtomicU64,
+}
+
+/// Fixed size hash table of previously searched positions, indexed by zobrist key. Lock-free, so
+/// it can be shared between search threads.
+pub struct TranspositionTable {
+    entries: Box<[Slot]>,
+}
+
+impl TranspositionTable {
+    pub fn new(size_mb: usize) -> Self {
+        Self::with_num_entries((size_mb * 1024 * 1024 / mem::size_of::<Slot>()).max(1))
+    }
+
+    /// An empty table of the same size.
+    pub(crate) fn new_empty_like(&self) -> Self {
+        Self::with_num_entries(self.entries.len())
+    }
+
+    fn with_num_entries(num_entries: usize) -> Self {
+        // Zeroed memory is allocated lazily by the OS, unlike initializing every slot, which
+        // takes a while for large tables
+        let entries = Box::<[Slot]>::new_zeroed_slice(num_entries);
+        // SAFETY: a zeroed slot is an empty one, atomics have the same bit validity as integers
+        let entries = unsafe { entries.assume_init() };
+        Self { entries }
+    }
+
+    pub fn clear(&self) {
+        for slot in &self.entries {
+            slot.key_xor_data.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
+    }
+
+    fn index(&self, key: u64) -> usize {
+        (key % self.entries.len() as u64) as usize
+    }
+
+    fn load(&self, idx: usize) -> Option<TranspositionTableEntry> {
+        let slot = &self.entries[idx];
+        let key_xor_data = slot.key_xor_data.load(Ordering::Relaxed);
+        let data = slot.data.load(Ordering::Relaxed);
+        (data & OCCUPIED_BIT != 0)
+            .then(|| TranspositionTableEntry::from_data(key_xor_data ^ data, data))
+    }
+
+    /// `ply` is how far the position is from the root, which mate scores are relative to.
+    pub(crate) fn probe(&self, key: u64, ply: u64) -> Option<TranspositionTableEntry> {
+        self.load(self.index(key))
+            .filter(|entry| entry.key == key)
+            .map(|entry| TranspositionTableEntry {
+                eval: entry.eval.to_root_relative(ply),
+                ..entry
+            })
+    }
+
+    /// `ply` is how far the position is from the root, which mate scores are relative to.
+    pub(crate) fn store(
+        &self,
+        key: u64,
+        depth: u8,
+        ply: u64,
+        bound: Bound,
+        eval: Eval,
+        best_move: Option<Move>,
+    ) {
+        let idx = self.index(key);
+
+        // Prefer keeping the results of deeper searches of the same position, but always
+        // replace entries for other positions
+        if let Some(entry) = self.load(idx) {
+            if entry.key == key && entry.depth > depth {
+                return;
+            }
+        }
+
+        let data = TranspositionTableEntry {
+            key,
+            depth,
+            bound,
+            eval: eval.to_node_relative(ply),
+            best_move,
+        }
+        .to_data();
+        let slot = &self.entries[idx];
+        slot.key_xor_data.store(key ^ data, Ordering::Relaxed);
+        slot.data.store(data, Ordering::Relaxed);
+    }
+
+    /// How full the table is, in permill. Estimated by sampling the start of the table, like
+    /// other engines do.
+    pub fn hashfull(&self) -> u16 {
+        let sample_size = self.entries.len().min(1000);
+        let num_full = (0..sample_size)
+            .filter(|&idx| self.load(idx).is_some())
+            .count();
+        (num_full * 1000 / sample_size) as u16
+    }
+}
+
+impl fmt::Debug for TranspositionTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Entries are left out, there are way too many to print
+        f.debug_struct("TranspositionTable")
+            .field("num_entries", &self.entries.len())
+            .finish()
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new(DEFAULT_TRANSPOSITION_TABLE_SIZE_MB)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use test_case::test_case;
+
+    use super::*;
+    use crate::bitboard::Square::*;
+
+    #[test]
+    fn test_store_probe() {
+        let tt = TranspositionTable::new(1);
+        let mve = Move::new(E2, E4);
+
+        assert_eq!(tt.probe(1234, 0), None);
+
+        tt.store(1234, 3, 0, Bound::Exact, Eval::cp(25), Some(mve));
+        let entry = tt.probe(1234, 0).unwrap();
+        assert_eq!(entry.depth, 3);
+        assert_eq!(entry.bound, Bound::Exact);
+        assert_eq!(entry.eval, Eval::cp(25));
+        assert_eq!(entry.best_move, Some(mve));
+
+        // Different key that maps to the same index shouldn't be returned
+        let colliding_key = 1234 + tt.entries.len() as u64;
+        assert_eq!(tt.probe(colliding_key, 0), None);
+    }
+
+    #[test_case(0, 0, Bound::Exact, Eval::DRAW, None ; "empty")]
+    #[test_case(u64::MAX, u8::MAX, Bound::Upper, Eval::INFINITY.flip(), Some(Move::new(H8, A1)) ; "extremes")]
+    #[test_case(42, 7, Bound::Lower, Eval::mated_in(3), Some(Move::with_promotion(B7, A8, Piece::Knight)) ; "promotion")]
+    fn test_entry_data(key: u64, depth: u8, bound: Bound, eval: Eval, best_move: Option<Move>) {
+        let entry = TranspositionTableEntry {
+            key,
+            depth,
+            bound,
+            eval,
+            best_move,
+        };
+        assert_eq!(
+            TranspositionTableEntry::from_data(key, entry.to_data()),
+            entry
+        );
+    }
+
+    #[test_case(Eval::mate_in(5), 2, 4, Eval::mate_in(7) ; "mate reached later")]
+    #[test_case(Eval::mate_in(5), 2, 1, Eval::mate_in(4) ; "mate reached sooner")]
+    #[test_case(Eval::mated_in(6), 3, 5, Eval::mated_in(8) ; "mated reached later")]
+    #[test_case(Eval::cp(-40), 3, 5, Eval::cp(-40) ; "score")]
+    fn test_mate_scores_follow_ply(eval: Eval, store_ply: u64, probe_ply: u64, want: Eval) {
+        let tt = TranspositionTable::new(1);
+        tt.store(1234, 3, store_ply, Bound::Exact, eval, None);
+        assert_eq!(tt.probe(1234, probe_ply).unwrap().eval, want);
+    }
+
+    #[test]
+    fn test_store_keeps_deeper_entry() {
+        let tt = TranspositionTable::new(1);
+
+        tt.store(1234, 5, 0, Bound::Lower, Eval::cp(10), None);
+        tt.store(1234, 2, 0, Bound::Exact, Eval::cp(-10), None);
+        assert_eq!(tt.probe(1234, 0).unwrap().depth, 5);
+
+        let colliding_key = 1234 + tt.entries.len() as u64;
+        tt.store(colliding_key, 1, 0, Bound::Exact, Eval::DRAW, None);
+        assert_eq!(tt.probe(1234, 0), None);
+        assert_eq!(tt.probe(colliding_key, 0).unwrap().depth, 1);
+    }
+
+    #[test]
+    fn test_hashfull_clear() {
+        let tt = TranspositionTable::new(1);
+        assert_eq!(tt.hashfull(), 0);
+
+        for key in 0..500 {
+            tt.store(key, 1, 0, Bound::Exact, Eval::DRAW, None);
+        }
+        assert_eq!(tt.hashfull(), 500);
+
+        tt.clear();
+        assert_eq!(tt.hashfull(), 0);
+    }
+
+    #[test]
+    fn test_concurrent_stores_are_never_torn() {
+        let tt = TranspositionTable::new(1);
+        let num_entries = tt.entries.len() as u64;
+        // Every thread stores its own keys to the same few slots, with a depth and eval derived
+        // from the key, so a torn entry would show up as a mismatch
+        let entry_for = |key: u64| ((key / num_entries) as u8, Eval::cp((key % 1000) as i32));
+        thread::scope(|s| {
+            for thread_num in 1..=4 {
+                let tt = &tt;
+                s.spawn(move || {
+                    for i in 0..10_000 {
+                        let key = i % 8 + thread_num * num_entries;
+                        let (depth, eval) = entry_for(key);
+                        tt.store(key, depth, 0, Bound::Exact, eval, None);
+                        for probe_key in (1..=4).map(|num| i % 8 + num * num_entries) {
+                            if let Some(entry) = tt.probe(probe_key, 0) {
+                                assert_eq!((entry.depth, entry.eval), entry_for(probe_key));
+                            }
+                        }
+                    }
+                });
+            }
+        });
+    }
+}
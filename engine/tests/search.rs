@@ -8,7 +8,8 @@ use test_case::test_case;
 
 use engine::Square::*;
 use engine::{
-    search, Move, Position, SearchParams, HYPERBOLA_QUINTESSENCE_MOVE_GEN, POSITION_EVALUATOR,
+    search, Game, GameStatus, GenerateMoves, Move, Position, SearchParams, TranspositionTable,
+    HYPERBOLA_QUINTESSENCE_MOVE_GEN, POSITION_EVALUATOR,
 };
 use testresult::TestResult;
 
@@ -20,13 +21,14 @@ fn test_search_terminates() {
     let terminate_cloned = Arc::clone(&terminate);
     let handle = thread::spawn(move || {
         let (best_move, _) = search(
-            &Position::start(),
+            &Game::new(Position::start()),
             &SearchParams {
                 move_time: Some(Duration::from_secs(2)),
                 ..SearchParams::default()
             },
             HYPERBOLA_QUINTESSENCE_MOVE_GEN,
             POSITION_EVALUATOR,
+            &TranspositionTable::default(),
             Arc::clone(&terminate_cloned),
         )
         .unwrap();
@@ -55,35 +57,440 @@ fn test_search_terminates() {
 #[test_case(Position::from_fen("2k5/q7/8/8/8/8/8/6QK w - - 0 1").unwrap(), 3, Move::new(G1, A7) ; "obvious queen capture empty board")]
 #[test_case(Position::from_fen("rnbqkbnr/ppp2ppp/8/3pp3/4P1Q1/2N5/PPPP1PPP/R1B1KBNR b KQkq - 0 1").unwrap(), 3, Move::new(C8, G4) ; "obvious queen capture full board")]
 #[test_case(Position::from_fen("7k/8/8/8/8/3r4/4r3/1K6 w - - 0 1").unwrap(), 3, Move::new(B1, C1) ; "obvious move to avoid mate")]
+#[test_case(Position::from_fen("4k3/3r4/8/3p4/8/8/3R4/3RK3 w - - 0 1").unwrap(), 2, Move::new(D2, D5) ; "recapture past the horizon")]
+#[test_case(Position::from_fen("7k/8/8/5K2/8/8/8/R7 w - - 0 1").unwrap(), 6, Move::new(F5, G6) ; "late quiet move mates in 2")]
 fn test_finds_best_move(position: Position, max_depth: u64, best_move_want: Move) -> TestResult {
     let search_params = SearchParams {
         max_depth: Some(max_depth),
         ..SearchParams::default()
     };
     let (best_move_got, _) = search(
-        &position,
+        &Game::new(position),
         &search_params,
         HYPERBOLA_QUINTESSENCE_MOVE_GEN,
         POSITION_EVALUATOR,
+        &TranspositionTable::default(),
         Arc::new(AtomicBool::new(false)),
     )?;
     assert_eq!(best_move_got, Some(best_move_want));
     Ok(())
 }
 
-#[test_case(Position::from_fen("k7/8/1R6/8/8/8/8/1R1K4 w - - 0 1").unwrap(), Move::new(B6, B7) ; "rook ladder stalemate")]
-fn test_doesnt_find_stalemate(position: Position, stalemate_move_dont_want: Move) -> TestResult {
+#[test_case(Position::from_fen("k7/8/1R6/8/8/8/8/1R1K4 w - - 0 1").unwrap(), 1, Move::new(B6, B7) ; "rook ladder stalemate")]
+#[test_case(Position::from_fen("k7/8/8/8/8/8/8/1Q2K3 w - - 0 1").unwrap(), 3, Move::new(B1, B6) ; "queen stalemate deeper")]
+fn test_doesnt_find_stalemate(
+    position: Position,
+    max_depth: u64,
+    stalemate_move_dont_want: Move,
+) -> TestResult {
     let search_params = SearchParams {
-        max_depth: Some(1),
+        max_depth: Some(max_depth),
         ..SearchParams::default()
     };
     let (best_move_got, _) = search(
-        &position,
+        &Game::new(position),
         &search_params,
         HYPERBOLA_QUINTESSENCE_MOVE_GEN,
         POSITION_EVALUATOR,
+        &TranspositionTable::default(),
         Arc::new(AtomicBool::new(false)),
     )?;
     assert_ne!(best_move_got, Some(stalemate_move_dont_want));
     Ok(())
 }
+
+#[test_case(Position::from_fen("4k3/8/3p4/4n3/8/8/4Q3/4K3 w - - 0 1").unwrap(), 1, Move::new(E2, E5) ; "queen takes defended knight")]
+#[test_case(Position::from_fen("4k3/8/8/b7/8/2n5/2Q5/4K3 w - - 0 1").unwrap(), 1, Move::new(C2, C3) ; "queen takes knight defended by bishop")]
+fn test_avoids_horizon_blunder(
+    position: Position,
+    max_depth: u64,
+    blunder_dont_want: Move,
+) -> TestResult {
+    let search_params = SearchParams {
+        max_depth: Some(max_depth),
+        ..SearchParams::default()
+    };
+    let (best_move_got, _) = search(
+        &Game::new(position),
+        &search_params,
+        HYPERBOLA_QUINTESSENCE_MOVE_GEN,
+        POSITION_EVALUATOR,
+        &TranspositionTable::default(),
+        Arc::new(AtomicBool::new(false)),
+    )?;
+    assert_ne!(best_move_got, Some(blunder_dont_want));
+    Ok(())
+}
+
+#[test_case(Position::from_fen("1q1k4/2Rr4/8/2Q3K1/8/8/8/8 w - - 0 1").unwrap(), 6, false, Move::new(G5, H6) ; "queen and rook")]
+#[test_case(Position::from_fen("1q1k4/2Rr4/8/2Q3K1/8/8/8/8 w - - 0 1").unwrap(), 6, true, Move::new(G5, H6) ; "queen and rook verified")]
+#[test_case(Position::from_fen("k7/p7/2K5/R7/8/8/8/8 w - - 0 1").unwrap(), 6, false, Move::new(C6, C7) ; "only pawns")]
+fn test_finds_zugzwang(
+    position: Position,
+    max_depth: u64,
+    null_move_verification: bool,
+    best_move_want: Move,
+) -> TestResult {
+    let search_params = SearchParams {
+        max_depth: Some(max_depth),
+        null_move_verification,
+        ..SearchParams::default()
+    };
+    let (best_move_got, _) = search(
+        &Game::new(position),
+        &search_params,
+        HYPERBOLA_QUINTESSENCE_MOVE_GEN,
+        POSITION_EVALUATOR,
+        &TranspositionTable::default(),
+        Arc::new(AtomicBool::new(false)),
+    )?;
+    assert_eq!(best_move_got, Some(best_move_want));
+    Ok(())
+}
+
+#[test]
+fn test_seldepth_includes_quiescence_search() -> TestResult {
+    let position =
+        Position::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")?;
+    let max_depth = 2;
+    let search_params = SearchParams {
+        max_depth: Some(max_depth),
+        ..SearchParams::default()
+    };
+    let (_, info) = search(
+        &Game::new(position),
+        &search_params,
+        HYPERBOLA_QUINTESSENCE_MOVE_GEN,
+        POSITION_EVALUATOR,
+        &TranspositionTable::default(),
+        Arc::new(AtomicBool::new(false)),
+    )?;
+    assert!(info.seldepth > max_depth);
+    Ok(())
+}
+
+#[test]
+fn test_extensions_find_forcing_mate() -> TestResult {
+    // Smothered mate in 4, which takes 7 plies, but is all checks
+    let position = Position::from_fen("r6k/6pp/8/6N1/2Q5/8/8/6K1 w - - 0 1")?;
+    let max_depth = 4;
+    let search_params = SearchParams {
+        max_depth: Some(max_depth),
+        ..SearchParams::default()
+    };
+    let (_, info) = search(
+        &Game::new(position),
+        &search_params,
+        HYPERBOLA_QUINTESSENCE_MOVE_GEN,
+        POSITION_EVALUATOR,
+        &TranspositionTable::default(),
+        Arc::new(AtomicBool::new(false)),
+    )?;
+    assert_eq!(
+        info.pv,
+        [
+            Move::new(G5, F7),
+            Move::new(H8, G8),
+            Move::new(F7, H6),
+            Move::new(G8, H8),
+            Move::new(C4, G8),
+            Move::new(A8, G8),
+            Move::new(H6, F7),
+        ]
+    );
+    assert!(info.seldepth >= 7);
+    Ok(())
+}
+
+#[test_case(Position::from_fen("k7/6R1/7R/8/8/8/8/3K4 w - - 0 1").unwrap(), 3, 1 ; "ends in mate")]
+#[test_case(Position::start(), 4, 4 ; "start position")]
+#[test_case(Position::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap(), 3, 3 ; "kiwipete")]
+fn test_pv_is_legal_line(position: Position, max_depth: u64, pv_len_want: usize) -> TestResult {
+    let search_params = SearchParams {
+        max_depth: Some(max_depth),
+        ..SearchParams::default()
+    };
+    let mut game = Game::new(position);
+    let (best_move_got, info) = search(
+        &game,
+        &search_params,
+        HYPERBOLA_QUINTESSENCE_MOVE_GEN,
+        POSITION_EVALUATOR,
+        &TranspositionTable::default(),
+        Arc::new(AtomicBool::new(false)),
+    )?;
+
+    assert_eq!(info.pv.len(), pv_len_want);
+    assert_eq!(info.pv.first().copied(), best_move_got);
+    for mve in &info.pv {
+        assert!(
+            HYPERBOLA_QUINTESSENCE_MOVE_GEN
+                .gen_moves(game.position())
+                .contains(mve),
+            "{} in {:?} is illegal",
+            mve,
+            info.pv
+        );
+        game.make_move(mve)?;
+    }
+    if pv_len_want < max_depth as usize {
+        assert_eq!(
+            game.status(HYPERBOLA_QUINTESSENCE_MOVE_GEN),
+            GameStatus::Checkmate
+        );
+    }
+    Ok(())
+}
+
+#[test_case(Position::from_fen("k7/6R1/7R/8/8/8/8/3K4 w - - 0 1").unwrap(), 1 ; "rook ladder in 1 white")]
+#[test_case(Position::from_fen("8/k7/8/8/8/1r6/r7/7K b - - 0 1").unwrap(), 1 ; "rook ladder in 1 black")]
+#[test_case(Position::from_fen("1k6/8/2R5/7R/8/8/8/6K1 w - - 0 1").unwrap(), 2 ; "rook ladder in 2 white")]
+#[test_case(Position::from_fen("8/k7/8/8/r7/5r2/8/6K1 b - - 0 1").unwrap(), 2 ; "rook ladder in 2 black")]
+#[test_case(Position::from_fen("7k/8/8/5K2/8/8/8/R7 w - - 0 1").unwrap(), 2 ; "quiet king move in 2")]
+#[test_case(Position::from_fen("r5k1/5Npp/8/8/2Q5/8/8/6K1 w - - 0 1").unwrap(), 3 ; "smothered mate in 3")]
+#[test_case(Position::from_fen("r1b3kr/ppp1Bp1p/1b6/n2P4/2p3q1/2Q2N2/P4PPP/RN2R1K1 w - - 0 1").unwrap(), 3 ; "queen sacrifice in 3")]
+#[test_case(Position::from_fen("r6k/6pp/8/6N1/2Q5/8/8/6K1 w - - 0 1").unwrap(), 4 ; "smothered mate in 4")]
+fn test_finds_mate(position: Position, mate: u64) -> TestResult {
+    let search_params = SearchParams {
+        mate: Some(mate),
+        ..SearchParams::default()
+    };
+    let mut game = Game::new(position);
+    let (best_move_got, info) = search(
+        &game,
+        &search_params,
+        HYPERBOLA_QUINTESSENCE_MOVE_GEN,
+        POSITION_EVALUATOR,
+        &TranspositionTable::default(),
+        Arc::new(AtomicBool::new(false)),
+    )?;
+
+    assert_eq!(info.eval.mate_moves(), Some(mate as i32));
+    assert_eq!(info.eval.to_string(), format!("mate {}", mate));
+    assert_eq!(info.pv.first().copied(), best_move_got);
+    assert_eq!(info.pv.len(), 2 * mate as usize - 1, "{:?}", info.pv);
+    for mve in &info.pv {
+        game.make_move(mve)?;
+    }
+    assert_eq!(
+        game.status(HYPERBOLA_QUINTESSENCE_MOVE_GEN),
+        GameStatus::Checkmate
+    );
+    Ok(())
+}
+
+#[test_case(Position::from_fen("r5k1/5Npp/8/8/2Q5/8/8/6K1 w - - 0 1").unwrap(), 2 ; "mate in 3")]
+#[test_case(Position::start(), 2 ; "start position")]
+fn test_doesnt_find_longer_mate(position: Position, mate: u64) -> TestResult {
+    let search_params = SearchParams {
+        mate: Some(mate),
+        ..SearchParams::default()
+    };
+    let (best_move_got, info) = search(
+        &Game::new(position),
+        &search_params,
+        HYPERBOLA_QUINTESSENCE_MOVE_GEN,
+        POSITION_EVALUATOR,
+        &TranspositionTable::default(),
+        Arc::new(AtomicBool::new(false)),
+    )?;
+
+    assert!(best_move_got.is_some());
+    assert!(!info
+        .eval
+        .mate_moves()
+        .is_some_and(|moves| moves > 0 && moves as u64 <= mate));
+    Ok(())
+}
+
+#[test]
+fn test_mate_search_keeps_transposition_table() -> TestResult {
+    let game = Game::new(Position::from_fen("r5k1/5Npp/8/8/2Q5/8/8/6K1 w - - 0 1")?);
+    let transposition_table = TranspositionTable::new(1);
+    let search_once = |params: SearchParams| {
+        search(
+            &game,
+            &params,
+            HYPERBOLA_QUINTESSENCE_MOVE_GEN,
+            POSITION_EVALUATOR,
+            &transposition_table,
+            Arc::new(AtomicBool::new(false)),
+        )
+    };
+
+    search_once(SearchParams {
+        max_depth: Some(4),
+        ..SearchParams::default()
+    })?;
+    let hashfull = transposition_table.hashfull();
+    assert!(hashfull > 0);
+
+    let (_, info) = search_once(SearchParams {
+        mate: Some(3),
+        ..SearchParams::default()
+    })?;
+    assert_eq!(info.eval.mate_moves(), Some(3));
+    assert_eq!(transposition_table.hashfull(), hashfull);
+    Ok(())
+}
+
+#[test_case(Position::start(), 4, 3, 3 ; "start position")]
+#[test_case(Position::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap(), 3, 4, 4 ; "kiwipete")]
+#[test_case(Position::from_fen("k7/8/8/8/8/8/8/K7 w - - 0 1").unwrap(), 3, 5, 3 ; "fewer moves than lines")]
+fn test_multi_pv_finds_best_lines(
+    position: Position,
+    max_depth: u64,
+    multi_pv: usize,
+    num_lines_want: usize,
+) -> TestResult {
+    let search_params = SearchParams {
+        max_depth: Some(max_depth),
+        multi_pv: Some(multi_pv),
+        ..SearchParams::default()
+    };
+    let game = Game::new(position);
+    let (best_move_got, info) = search(
+        &game,
+        &search_params,
+        HYPERBOLA_QUINTESSENCE_MOVE_GEN,
+        POSITION_EVALUATOR,
+        &TranspositionTable::default(),
+        Arc::new(AtomicBool::new(false)),
+    )?;
+
+    assert_eq!(info.lines.len(), num_lines_want);
+    assert_eq!(info.lines[0].eval, info.eval);
+    assert_eq!(info.lines[0].pv, info.pv);
+    assert_eq!(info.pv.first().copied(), best_move_got);
+    for (line, next_line) in info.lines.iter().zip(info.lines.iter().skip(1)) {
+        assert!(line.eval >= next_line.eval);
+        assert_ne!(line.pv[0], next_line.pv[0]);
+    }
+    for line in &info.lines {
+        let mut game = game.clone();
+        for mve in &line.pv {
+            assert!(
+                HYPERBOLA_QUINTESSENCE_MOVE_GEN
+                    .gen_moves(game.position())
+                    .contains(mve),
+                "{} in {:?} is illegal",
+                mve,
+                line.pv
+            );
+            game.make_move(mve)?;
+        }
+    }
+    Ok(())
+}
+
+#[test_case(Position::from_fen("1k6/8/2R5/7R/8/8/8/6K1 w - - 0 1").unwrap(), 3, Move::new(H5, H7) ; "rook ladder in 3 white")]
+#[test_case(Position::from_fen("r5k1/5Npp/8/8/2Q5/8/8/6K1 w - - 0 1").unwrap(), 5, Move::new(F7, H6) ; "smothered mate in 3")]
+fn test_multi_threaded_search_finds_best_move(
+    position: Position,
+    max_depth: u64,
+    best_move_want: Move,
+) -> TestResult {
+    let search_params = SearchParams {
+        max_depth: Some(max_depth),
+        threads: Some(4),
+        ..SearchParams::default()
+    };
+    let (best_move_got, info) = search(
+        &Game::new(position),
+        &search_params,
+        HYPERBOLA_QUINTESSENCE_MOVE_GEN,
+        POSITION_EVALUATOR,
+        &TranspositionTable::default(),
+        Arc::new(AtomicBool::new(false)),
+    )?;
+
+    assert_eq!(best_move_got, Some(best_move_want));
+    assert_eq!(info.pv.first().copied(), best_move_got);
+    Ok(())
+}
+
+#[test_case(1, 0 ; "one thread")]
+// The other threads' nodes are only counted in batches of a few hundred
+#[test_case(4, 1_000 ; "four threads")]
+fn test_search_stops_at_max_nodes(threads: usize, slack: u64) -> TestResult {
+    let max_nodes = 20_000;
+    let search_params = SearchParams {
+        max_nodes: Some(max_nodes),
+        threads: Some(threads),
+        ..SearchParams::default()
+    };
+    let (best_move_got, info) = search(
+        &Game::new(Position::start()),
+        &search_params,
+        HYPERBOLA_QUINTESSENCE_MOVE_GEN,
+        POSITION_EVALUATOR,
+        &TranspositionTable::default(),
+        Arc::new(AtomicBool::new(false)),
+    )?;
+
+    assert!(best_move_got.is_some());
+    assert!(
+        info.positions_processed <= max_nodes + slack,
+        "{} nodes",
+        info.positions_processed
+    );
+    Ok(())
+}
+
+#[test]
+fn test_single_threaded_search_is_reproducible() -> TestResult {
+    let search_params = SearchParams {
+        max_depth: Some(5),
+        threads: Some(1),
+        ..SearchParams::default()
+    };
+    let game = Game::new(Position::from_fen(
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    )?);
+    let mut results = Vec::new();
+    for _ in 0..2 {
+        let (best_move, info) = search(
+            &game,
+            &search_params,
+            HYPERBOLA_QUINTESSENCE_MOVE_GEN,
+            POSITION_EVALUATOR,
+            &TranspositionTable::default(),
+            Arc::new(AtomicBool::new(false)),
+        )?;
+        results.push((best_move, info.positions_processed, info.eval, info.pv));
+    }
+
+    assert_eq!(results[0], results[1]);
+    Ok(())
+}
+
+#[test_case(1 ; "depth 1")]
+#[test_case(3 ; "depth 3")]
+fn test_finds_repetition_when_losing(max_depth: u64) -> TestResult {
+    let mut game = Game::new(Position::from_fen("k7/8/8/8/8/8/1q6/4K1N1 w - - 0 1")?);
+    for mve in [
+        Move::new(G1, H3),
+        Move::new(B2, A2),
+        Move::new(H3, G1),
+        Move::new(A2, B2),
+    ] {
+        game.make_move(&mve)?;
+    }
+
+    let search_params = SearchParams {
+        max_depth: Some(max_depth),
+        ..SearchParams::default()
+    };
+    let (best_move_got, _) = search(
+        &game,
+        &search_params,
+        HYPERBOLA_QUINTESSENCE_MOVE_GEN,
+        POSITION_EVALUATOR,
+        &TranspositionTable::default(),
+        Arc::new(AtomicBool::new(false)),
+    )?;
+
+    // Down a queen, so repeating the position is the best white can do
+    assert_eq!(best_move_got, Some(Move::new(G1, H3)));
+    Ok(())
+}
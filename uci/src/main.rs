@@ -5,18 +5,19 @@ use std::{
 };
 
 use anyhow::{Context, Result};
-use engine::{HyperbolaQuintessenceMoveGen, HYPERBOLA_QUINTESSENCE_MOVE_GEN};
+use engine::NativeMoveGen;
 use tracing::{debug, level_filters::LevelFilter, warn, Level};
 use tracing_subscriber::{layer::SubscriberExt, prelude::*, util::SubscriberInitExt, Registry};
 
 use uci::{LOGS_DIRECTORY, UCI};
 
-static MOVE_GEN: HyperbolaQuintessenceMoveGen = HYPERBOLA_QUINTESSENCE_MOVE_GEN;
-
 fn main() -> Result<()> {
     enable_logging()?;
 
-    let mut uci = UCI::new(MOVE_GEN);
+    let move_gen = NativeMoveGen::detect();
+    debug!("move generator: {}", move_gen);
+
+    let mut uci = UCI::new(move_gen);
 
     //uci.handle_command("uci");
     //uci.handle_command("debug on");
@@ -5,7 +5,7 @@ use std::str::FromStr;
 use std::time::Duration;
 
 use anyhow::Result;
-use engine::{Move, SearchParams, Side, Square};
+use engine::{Move, PositionError, SearchParams, Side};
 use winnow::ascii::{alphanumeric1, digit1};
 use winnow::combinator::{alt, opt, preceded, rest, separated, terminated};
 use winnow::token::{one_of, take_until, take_while};
@@ -63,6 +63,7 @@ pub(crate) enum UCICommand {
         depth: usize,
     },
     PerftBenchmark,
+    SearchBenchmark,
 }
 
 impl fmt::Display for UCICommand {
@@ -107,6 +108,7 @@ impl fmt::Display for UCICommand {
             UCICommand::Perft { depth } => write!(f, "go perft {}", depth),
             UCICommand::PerftFull { depth } => write!(f, "go perft_full {}", depth),
             UCICommand::PerftBenchmark => write!(f, "perft_bench"),
+            UCICommand::SearchBenchmark => write!(f, "search_bench"),
         }
     }
 }
@@ -203,6 +205,7 @@ impl FromStr for UCICommand {
             parse_perft,
             parse_perft_full,
             parse_perft_benchmark,
+            parse_search_benchmark,
             parse_go,
         ))
         .parse(input)
@@ -281,24 +284,16 @@ fn parse_position(input: &mut &str) -> PResult<UCICommand> {
             opt(preceded(" moves ", rest)),
         )
             .try_map(|(fen, moves): (Option<String>, Option<&str>)| {
-                Ok::<UCICommand, <Square as FromStr>::Err>(UCICommand::Position {
+                Ok::<UCICommand, PositionError>(UCICommand::Position {
                     fen: fen.map(|s: String| s.to_string()),
-                    moves: moves.map(|moves: &str| {
-                        {
-                            moves.split(' ').map(|mve_str| {
-                                let (src_str, dest_str) = mve_str.split_at(2);
-                                let src = Square::from_str(src_str.to_uppercase().as_str())?;
-                                let dest = Square::from_str(dest_str.to_uppercase().as_str())?;
-                                Ok::<Move, <Square as FromStr>::Err>(Move {
-                                    src,
-                                    dest,
-                                    promotion: None,
-                                })
-                            })
-                        }
-                        .collect::<Result<Vec<Move>, _>>()
-                        .unwrap()
-                    }),
+                    moves: moves
+                        .map(|moves: &str| {
+                            moves
+                                .split(' ')
+                                .map(Move::from_str)
+                                .collect::<Result<Vec<Move>, _>>()
+                        })
+                        .transpose()?,
                 })
             }),
     )
@@ -366,6 +361,12 @@ fn parse_perft_benchmark(input: &mut &str) -> PResult<UCICommand> {
         .parse_next(input)
 }
 
+fn parse_search_benchmark(input: &mut &str) -> PResult<UCICommand> {
+    "search_bench"
+        .value(UCICommand::SearchBenchmark)
+        .parse_next(input)
+}
+
 // ======================================================
 // Winnow Parsing functions (go commands)
 // ======================================================
@@ -463,6 +464,7 @@ fn parse_go(input: &mut &str) -> PResult<UCICommand> {
             }
         }),
         infinite: params.iter().any(|i| matches!(i, GoParameter::Infinite)),
+        ..SearchParams::default()
     })
     .map(|search_params: SearchParams| UCICommand::Go {
         params: search_params,
@@ -473,19 +475,10 @@ fn parse_go(input: &mut &str) -> PResult<UCICommand> {
 fn parse_go_searchmoves(input: &mut &str) -> PResult<GoParameter> {
     preceded("searchmoves ", rest)
         .try_map(|moves: &str| {
-            Ok::<GoParameter, <Square as FromStr>::Err>(GoParameter::SearchMoves {
+            Ok::<GoParameter, PositionError>(GoParameter::SearchMoves {
                 moves: moves
                     .split(' ')
-                    .map(|mve_str| {
-                        let (src_str, dest_str) = mve_str.split_at(2);
-                        let src = Square::from_str(src_str.to_uppercase().as_str())?;
-                        let dest = Square::from_str(dest_str.to_uppercase().as_str())?;
-                        Ok::<Move, <Square as FromStr>::Err>(Move {
-                            src,
-                            dest,
-                            promotion: None,
-                        })
-                    })
+                    .map(Move::from_str)
                     .collect::<Result<Vec<Move>, _>>()?,
             })
         })
@@ -580,7 +573,8 @@ mod tests {
     use test_case::test_case;
     use testresult::TestResult;
 
-    use engine::Square::*;
+    use engine::Piece;
+    use engine::Square::{self, *};
 
     #[test_case("uci", UCICommand::UCI)]
     #[test_case("debug on", UCICommand::Debug { on: true })]
@@ -595,6 +589,7 @@ mod tests {
     #[test_case("position startpos moves e2e4 e7e5", UCICommand::Position { fen: None, moves: Some(vec![Move::new(E2, E4), Move::new(E7, E5)])} ; "position startpos moves e2e4 e7e5")]
     #[test_case("position fen 8/8/4Rp2/5P2/1PP1pkP1/7P/1P1r4/7K b - - 0 40", UCICommand::Position { fen: Some("8/8/4Rp2/5P2/1PP1pkP1/7P/1P1r4/7K b - - 0 40".to_string()), moves: None} ; "position fen 8/8/4Rp2/5P2/1PP1pkP1/7P/1P1r4/7K b - - 0 40")]
     #[test_case("position fen r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1 moves f3f6", UCICommand::Position { fen: Some("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1".to_string()), moves: Some(vec![Move::new(F3, F6)])} ; "position fen r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1 moves f3f6")]
+    #[test_case("position fen 8/P7/8/8/8/8/8/k6K w - - 0 1 moves a7a8q", UCICommand::Position { fen: Some("8/P7/8/8/8/8/8/k6K w - - 0 1".to_string()), moves: Some(vec![Move::with_promotion(A7, A8, Piece::Queen)])} ; "position fen moves promotion")]
     #[test_case("stop", UCICommand::Stop)]
     #[test_case("ponderhit", UCICommand::PonderHit)]
     #[test_case("quit", UCICommand::Quit)]
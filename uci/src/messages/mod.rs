@@ -4,4 +4,4 @@ mod responses;
 pub use commands::{ReadUCICommand, UCICommandParseError, UCICommandStdinReader};
 
 pub(crate) use commands::UCICommand;
-pub(crate) use responses::UCIResponse;
+pub(crate) use responses::{UCIOption, UCIOptionType, UCIResponse};
@@ -2,13 +2,25 @@ use engine::Move;
 
 #[derive(Debug)]
 pub(crate) enum UCIResponse {
-    IDName { name: String },
-    IDAuthor { author: String },
+    IDName {
+        name: String,
+    },
+    IDAuthor {
+        author: String,
+    },
     UCIOk,
     ReadyOk,
-    BestMove { mve: Move, ponder: Option<Move> },
-    Info { info: Info },
-    Option { option: UCIOption },
+    /// `mve` is `None` when there are no legal moves, sent as the null move `0000`
+    BestMove {
+        mve: Option<Move>,
+        ponder: Option<Move>,
+    },
+    Info {
+        info: Info,
+    },
+    Option {
+        option: UCIOption,
+    },
 }
 
 #[derive(Debug)]
@@ -77,9 +89,9 @@ enum Score {
 
 #[derive(Debug)]
 pub struct UCIOption {
-    name: String,
-    type_: UCIOptionType,
-    default: Option<String>,
+    pub(crate) name: String,
+    pub(crate) type_: UCIOptionType,
+    pub(crate) default: Option<String>,
 }
 
 #[derive(Debug)]
@@ -98,15 +110,62 @@ impl std::fmt::Display for UCIResponse {
             UCIResponse::IDAuthor { author } => format!("id author {}", author),
             UCIResponse::UCIOk => "uciok".to_string(),
             UCIResponse::ReadyOk => "readyok".to_string(),
-            UCIResponse::BestMove { mve, ponder: None } => {
+            UCIResponse::BestMove {
+                mve: Some(mve),
+                ponder: None,
+            } => {
                 format!("bestmove {}", mve.to_string().to_lowercase())
             }
+            UCIResponse::BestMove {
+                mve: Some(mve),
+                ponder: Some(ponder),
+            } => {
+                format!(
+                    "bestmove {} ponder {}",
+                    mve.to_string().to_lowercase(),
+                    ponder.to_string().to_lowercase()
+                )
+            }
+            UCIResponse::BestMove {
+                mve: None,
+                ponder: None,
+            } => "bestmove 0000".to_string(),
+            UCIResponse::Option { option } => option.to_string(),
             _ => format!("{:?} not implemented", self),
         };
         write!(f, "{}", res_str)
     }
 }
 
+impl std::fmt::Display for UCIOption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "option name {} type ", self.name)?;
+        match &self.type_ {
+            UCIOptionType::Check => write!(f, "check")?,
+            UCIOptionType::Spin { .. } => write!(f, "spin")?,
+            UCIOptionType::Combo { .. } => write!(f, "combo")?,
+            UCIOptionType::Button => write!(f, "button")?,
+            UCIOptionType::String { .. } => write!(f, "string")?,
+        }
+        if let Some(default) = &self.default {
+            write!(f, " default {}", default)?;
+        }
+        match &self.type_ {
+            UCIOptionType::Spin {
+                range_start,
+                range_end,
+            } => write!(f, " min {} max {}", range_start, range_end),
+            UCIOptionType::Combo { options } => {
+                for option in options {
+                    write!(f, " var {}", option)?;
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
 impl Into<String> for UCIResponse {
     fn into(self) -> String {
         self.to_string()
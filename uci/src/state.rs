@@ -5,17 +5,18 @@ use std::collections::HashMap;
 use std::fs::File;
 use std::io::{stdout, Write};
 use std::process;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use std::{sync::atomic::AtomicBool, thread};
 use tracing::{debug, info, warn};
 
 use engine::{
-    perft, perft_full, search, EvaluatePosition, GenerateMoves, Move, Position, AUTHOR,
-    HYPERBOLA_QUINTESSENCE_MOVE_GEN, NAME, POSITION_EVALUATOR,
+    perft, perft_full, search, EvaluatePosition, Game, GameStatus, GenerateMoves, Move, Position,
+    SearchParams, TranspositionTable, AUTHOR, DEFAULT_TRANSPOSITION_TABLE_SIZE_MB, MAX_MOVES, NAME,
+    POSITION_EVALUATOR,
 };
 
-use crate::messages::{UCICommand, UCIResponse};
+use crate::messages::{UCICommand, UCIOption, UCIOptionType, UCIResponse};
 use crate::response_writer::{self, ResponseWriter};
 use crate::LOGS_DIRECTORY;
 
@@ -29,9 +30,55 @@ where
     // to be able store this as statig state local storage because that requires the
     // item to be a reference.
     maybe_terminate: Option<Arc<AtomicBool>>,
+    // Shared with the search threads, and kept between searches so results carry over
+    transposition_table: Arc<TranspositionTable>,
+    null_move_verification: bool,
+    multi_pv: usize,
+    threads: usize,
     start_time: DateTime<Local>,
 }
 
+const HASH_OPTION_NAME: &str = "Hash";
+const MAX_HASH_SIZE_MB: usize = 1024;
+const NULL_MOVE_VERIFICATION_OPTION_NAME: &str = "NullMoveVerification";
+const MULTI_PV_OPTION_NAME: &str = "MultiPV";
+const THREADS_OPTION_NAME: &str = "Threads";
+const MAX_THREADS: usize = 256;
+
+fn uci_options() -> Vec<UCIOption> {
+    vec![
+        UCIOption {
+            name: HASH_OPTION_NAME.to_string(),
+            type_: UCIOptionType::Spin {
+                range_start: 1,
+                range_end: MAX_HASH_SIZE_MB as i32,
+            },
+            default: Some(DEFAULT_TRANSPOSITION_TABLE_SIZE_MB.to_string()),
+        },
+        UCIOption {
+            name: NULL_MOVE_VERIFICATION_OPTION_NAME.to_string(),
+            type_: UCIOptionType::Check,
+            default: Some(false.to_string()),
+        },
+        UCIOption {
+            name: MULTI_PV_OPTION_NAME.to_string(),
+            type_: UCIOptionType::Spin {
+                range_start: 1,
+                range_end: MAX_MOVES as i32,
+            },
+            default: Some(1.to_string()),
+        },
+        UCIOption {
+            name: THREADS_OPTION_NAME.to_string(),
+            type_: UCIOptionType::Spin {
+                range_start: 1,
+                range_end: MAX_THREADS as i32,
+            },
+            default: Some(1.to_string()),
+        },
+    ]
+}
+
 impl<G> UCIState<G>
 where
     G: GenerateMoves + Copy + Send + Sync + 'static,
@@ -40,6 +87,10 @@ where
         Self {
             move_gen,
             maybe_terminate: None,
+            transposition_table: Arc::new(TranspositionTable::default()),
+            null_move_verification: false,
+            multi_pv: 1,
+            threads: 1,
             start_time: Local::now(),
         }
     }
@@ -85,9 +136,12 @@ where
                         author: AUTHOR.to_string(),
                     },
                 );
+                for option in uci_options() {
+                    info!("{}", UCIResponse::Option { option });
+                }
                 info!("{}", UCIResponse::UCIOk);
 
-                Transition(State::uci_enabled(Position::start()))
+                Transition(State::uci_enabled(Game::new(Position::start())))
             }
             _ => Super,
         }
@@ -120,20 +174,71 @@ where
     }
 
     #[state(superstate = "is_ready")]
-    fn uci_enabled(&mut self, position: &mut Position, event: &UCICommand) -> Response<State> {
+    fn uci_enabled(&mut self, game: &mut Game, event: &UCICommand) -> Response<State> {
         match event {
-            UCICommand::UCINewGame => Transition(State::uci_enabled(Position::start())),
+            UCICommand::UCINewGame => {
+                self.transposition_table.clear();
+                Transition(State::uci_enabled(Game::new(Position::start())))
+            }
+            UCICommand::SetOption { name, value } => {
+                if name.eq_ignore_ascii_case(HASH_OPTION_NAME) {
+                    match value.as_deref().map(str::parse::<usize>) {
+                        Some(Ok(size_mb)) if (1..=MAX_HASH_SIZE_MB).contains(&size_mb) => {
+                            self.transposition_table = Arc::new(TranspositionTable::new(size_mb));
+                        }
+                        _ => warn!("Invalid value for option {}: {:?}", name, value),
+                    }
+                } else if name.eq_ignore_ascii_case(NULL_MOVE_VERIFICATION_OPTION_NAME) {
+                    match value.as_deref().map(str::parse::<bool>) {
+                        Some(Ok(on)) => self.null_move_verification = on,
+                        _ => warn!("Invalid value for option {}: {:?}", name, value),
+                    }
+                } else if name.eq_ignore_ascii_case(MULTI_PV_OPTION_NAME) {
+                    match value.as_deref().map(str::parse::<usize>) {
+                        Some(Ok(lines)) if (1..=MAX_MOVES).contains(&lines) => {
+                            self.multi_pv = lines
+                        }
+                        _ => warn!("Invalid value for option {}: {:?}", name, value),
+                    }
+                } else if name.eq_ignore_ascii_case(THREADS_OPTION_NAME) {
+                    match value.as_deref().map(str::parse::<usize>) {
+                        Some(Ok(threads)) if (1..=MAX_THREADS).contains(&threads) => {
+                            self.threads = threads
+                        }
+                        _ => warn!("Invalid value for option {}: {:?}", name, value),
+                    }
+                } else {
+                    warn!("Unknown option: {}", name);
+                }
+                Handled
+            }
             UCICommand::Position { fen, moves } => {
-                let mut pos = match fen {
-                    Some(fen) => Position::from_fen(fen).unwrap(),
+                let pos = match fen {
+                    Some(fen) => match Position::from_fen(fen) {
+                        Ok(pos) => pos,
+                        Err(err) => {
+                            warn!("Invalid position, keeping the current one: {}", err);
+                            return Handled;
+                        }
+                    },
                     None => Position::start(),
                 };
+                // Moves are replayed into a game, rather than just the position, so search knows
+                // which positions have already occurred
+                let mut game = Game::new(pos);
                 if let Some(moves) = moves {
                     for mve in moves {
-                        pos.make_move(mve).unwrap();
+                        if !self.move_gen.is_legal(game.position(), mve) {
+                            warn!(
+                                "Illegal move {}, keeping the current position",
+                                mve.to_string().to_lowercase()
+                            );
+                            return Handled;
+                        }
+                        game.make_move(mve).unwrap();
                     }
                 }
-                Transition(State::uci_enabled(pos))
+                Transition(State::uci_enabled(game))
             }
             UCICommand::Go { params } => {
                 if let Some(terminate) = &self.maybe_terminate {
@@ -142,26 +247,49 @@ where
                         return Handled;
                     }
                 }
+                // Draws by the fifty move rule or repetition still have to be claimed, so only
+                // refuse to search when there are no moves to play
+                let status = game.status(self.move_gen);
+                if matches!(status, GameStatus::Checkmate | GameStatus::Stalemate) {
+                    info!("info string game is over: {}", status);
+                    info!(
+                        "{}",
+                        &UCIResponse::BestMove {
+                            mve: None,
+                            ponder: None
+                        }
+                    );
+                    return Handled;
+                }
+
                 let terminate = Arc::new(AtomicBool::new(false));
                 self.maybe_terminate = Some(Arc::clone(&terminate));
-                let search_position = position.clone();
+                let search_game = game.clone();
                 let move_gen = self.move_gen;
-                let params = params.clone();
+                let params = SearchParams {
+                    null_move_verification: self.null_move_verification,
+                    multi_pv: Some(self.multi_pv),
+                    threads: Some(self.threads),
+                    ..params.clone()
+                };
+                let transposition_table = Arc::clone(&self.transposition_table);
 
                 thread::spawn(move || {
-                    let (best_move, _) = search(
-                        &search_position,
+                    let (best_move, search_info) = search(
+                        &search_game,
                         &params,
                         move_gen,
                         POSITION_EVALUATOR,
+                        &transposition_table,
                         Arc::clone(&terminate),
                     )
                     .unwrap();
                     info!(
                         "{}",
                         &UCIResponse::BestMove {
-                            mve: best_move.expect("Best move should have been found"),
-                            ponder: None,
+                            mve: best_move,
+                            // The reply the engine expects, which the GUI can ponder on
+                            ponder: search_info.pv.get(1).copied(),
                         }
                     );
                     terminate.store(true, std::sync::atomic::Ordering::Relaxed);
@@ -178,25 +306,24 @@ where
                 Handled
             }
             UCICommand::Quit => {
-                debug!("Exiting with position fen: {}", position.to_fen());
+                debug!("Exiting with position fen: {}", game.position().to_fen());
                 process::exit(0);
             }
             UCICommand::Eval => {
-                let eval = POSITION_EVALUATOR.evaluate(position, HYPERBOLA_QUINTESSENCE_MOVE_GEN);
+                let eval = POSITION_EVALUATOR.evaluate(game.position(), self.move_gen);
                 info!("info string {}", eval);
                 Handled
             }
             UCICommand::Perft { depth } => {
                 let start = Instant::now();
-                let (move_counts, total_count) =
-                    perft(position, *depth, HYPERBOLA_QUINTESSENCE_MOVE_GEN);
+                let (move_counts, total_count) = perft(game.position(), *depth, self.move_gen);
                 let time_elapsed = start.elapsed();
 
                 write_perft_results(move_counts, total_count, time_elapsed);
                 Handled
             }
             UCICommand::PerftFull { depth } => {
-                let perft_results = perft_full(position, *depth, HYPERBOLA_QUINTESSENCE_MOVE_GEN);
+                let perft_results = perft_full(game.position(), *depth, self.move_gen);
                 info!("{}", perft_results);
                 Handled
             }
@@ -204,6 +331,10 @@ where
                 self.perft_benchmark().unwrap();
                 Handled
             }
+            UCICommand::SearchBenchmark => {
+                self.search_benchmark().unwrap();
+                Handled
+            }
             _ => Super,
         }
     }
@@ -217,7 +348,7 @@ where
             let position = Position::from_fen(fen)?;
             let position_start = Instant::now();
             let (position_move_nodes, position_total_nodes) =
-                perft(&position, *depth, HYPERBOLA_QUINTESSENCE_MOVE_GEN);
+                perft(&position, *depth, self.move_gen);
             let position_time_elapsed = position_start.elapsed();
 
             total_nodes += position_total_nodes;
@@ -236,6 +367,42 @@ where
         info!("Nodes/second: {:.0}", total_nodes_per_second);
         Ok(())
     }
+
+    /// Fixed depth searches, so the node counts show how well the search prunes and can be
+    /// compared between versions.
+    fn search_benchmark(&mut self) -> Result<()> {
+        let total_start = Instant::now();
+        let mut total_nodes = 0;
+        for (fen, depth) in SEARCH_BENCHMARK_FENS_AND_DEPTHS {
+            info!("Position: [{}], depth {}", fen, depth);
+
+            let params = SearchParams {
+                max_depth: Some(*depth),
+                ..SearchParams::default()
+            };
+            // Each search starts from an empty table, so earlier searches don't affect it
+            let (_, search_info) = search(
+                &Game::new(Position::from_fen(fen)?),
+                &params,
+                self.move_gen,
+                POSITION_EVALUATOR,
+                &TranspositionTable::default(),
+                Arc::new(AtomicBool::new(false)),
+            )?;
+
+            total_nodes += search_info.positions_processed;
+
+            info!("Time (ms): {}", search_info.time_elapsed.as_millis());
+            info!("Nodes searched: {}", search_info.positions_processed);
+        }
+        let total_time_elapsed = total_start.elapsed();
+        let total_nodes_per_second = total_nodes as f64 / total_time_elapsed.as_secs_f64();
+        info!("\n===========================");
+        info!("Total time (ms): {}", total_time_elapsed.as_millis());
+        info!("Nodes searched: {}", total_nodes);
+        info!("Nodes/second: {:.0}", total_nodes_per_second);
+        Ok(())
+    }
 }
 
 fn write_perft_results(
@@ -302,3 +469,19 @@ const PERFT_BENCHMARK_FENS_AND_DEPTHS: &[(&str, usize)] = &[
     ),
     ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 5),
 ];
+
+const SEARCH_BENCHMARK_FENS_AND_DEPTHS: &[(&str, u64)] = &[
+    (
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        5,
+    ),
+    (
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        4,
+    ),
+    ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 6),
+    (
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+        4,
+    ),
+];